@@ -0,0 +1,114 @@
+//! Compares wall lookups on the dense tile grid against a linear scan over the wall list,
+//! which is how `Map::has_space` used to work, and times a long headless run.
+//!
+//! Run with `cargo bench --bench map`.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use bevy::math::IVec2;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use dungeon_vac::core::{
+    command::Command, map::Map, physics::Physics, program::Program, rule::Rule, runner,
+    sensor::Sensor,
+};
+
+const SIZE: usize = 500;
+
+/// a SIZE x SIZE map with an outer wall and randomly scattered walls inside
+fn generate_map() -> Map {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let mut str = String::new();
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let border = x == 0 || y == 0 || x == SIZE - 1 || y == SIZE - 1;
+            let char = if (x, y) == (1, 1) {
+                'S'
+            } else if border || rng.random_bool(0.2) {
+                '#'
+            } else {
+                '.'
+            };
+            str.push(char);
+        }
+        str.push('\n');
+    }
+
+    Map::parse(&str).unwrap()
+}
+
+fn has_space_linear(map: &Map, pt: IVec2) -> bool {
+    let (width, height) = map.dimensions();
+
+    pt.x >= 0
+        && pt.y >= 0
+        && pt.x < width as i32
+        && pt.y < height as i32
+        && !map.walls().contains(&pt)
+}
+
+fn time(name: &str, queries: usize, f: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    f();
+    let elapsed = start.elapsed();
+
+    println!(
+        "{name:<24} {queries:>8} lookups in {elapsed:>12.3?} ({:.1} ns/lookup)",
+        elapsed.as_nanos() as f64 / queries as f64
+    );
+
+    elapsed
+}
+
+fn main() {
+    let map = generate_map();
+    println!(
+        "{SIZE}x{SIZE} map with {} walls\n",
+        black_box(map.walls().len())
+    );
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let points = (0..100_000)
+        .map(|_| {
+            IVec2::new(
+                rng.random_range(0..SIZE as i32),
+                rng.random_range(0..SIZE as i32),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // the linear scan is slow enough that a small sample is plenty
+    let linear_points = &points[..1_000];
+
+    let grid = time("has_space (grid)", points.len(), || {
+        for pt in &points {
+            black_box(map.has_space(*pt));
+        }
+    });
+    let linear = time("has_space (linear scan)", linear_points.len(), || {
+        for pt in linear_points {
+            black_box(has_space_linear(&map, *pt));
+        }
+    });
+
+    let grid_per_lookup = grid.as_secs_f64() / points.len() as f64;
+    let linear_per_lookup = linear.as_secs_f64() / linear_points.len() as f64;
+    println!("speedup: {:.0}x\n", linear_per_lookup / grid_per_lookup);
+
+    let program = Program::new([
+        Rule::new(Sensor::SpaceRight, Command::TurnRight),
+        Rule::new(Sensor::HitWall, Command::TurnLeft),
+    ]);
+
+    let start = Instant::now();
+    let report = runner::run(&map, &program, Physics::default(), 100_000);
+    println!(
+        "runner::run: {} ticks in {:.3?}",
+        report.ticks(),
+        start.elapsed()
+    );
+}
@@ -0,0 +1,144 @@
+use std::process::ExitCode;
+
+use bevy::math::IVec2;
+
+use dungeon_vac::core::{
+    dir::Dir,
+    dsl,
+    map::Map,
+    physics::Physics,
+    runner::{self, RunReport},
+};
+
+const DEFAULT_TICK_LIMIT: usize = 1000;
+
+const USAGE: &str = "usage: dungeon-vac-cli <map file> <rules file> [--ticks <limit>]";
+
+struct Args {
+    map_path: String,
+    rules_path: String,
+    tick_limit: usize,
+    physics: Physics,
+}
+
+fn usage() -> String {
+    format!("{USAGE} {}", Physics::USAGE)
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut tick_limit = DEFAULT_TICK_LIMIT;
+    let mut physics = Physics::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ticks" => {
+                let value = args.next().ok_or("Missing value for --ticks")?;
+                tick_limit = value
+                    .parse()
+                    .map_err(|_| format!("Invalid tick limit: '{value}'"))?;
+            }
+            "-h" | "--help" => return Err(usage()),
+            _ if physics.apply_arg(&arg, &mut args)? => {}
+            _ => positional.push(arg),
+        }
+    }
+
+    let [map_path, rules_path] = <[String; 2]>::try_from(positional).map_err(|_| usage())?;
+
+    Ok(Args {
+        map_path,
+        rules_path,
+        tick_limit,
+        physics,
+    })
+}
+
+fn heading_char(dir: Dir) -> char {
+    match dir {
+        Dir::East => '>',
+        Dir::North => '^',
+        Dir::West => '<',
+        Dir::South => 'v',
+    }
+}
+
+/// Draws the map at the end of the run, with the tiles the vac visited marked with `o`
+fn render(map: &Map, report: &RunReport) -> String {
+    let (width, height) = map.dimensions();
+    let state = &report.final_state;
+
+    let mut out = String::new();
+    for y in (0..height as i32).rev() {
+        for x in 0..width as i32 {
+            let pt = IVec2::new(x, y);
+
+            let char = if pt == state.vac_pos() {
+                heading_char(state.vac_dir())
+            } else if !map.has_space(pt) {
+                '#'
+            } else if state.is_dirty(map, pt) {
+                '*'
+            } else if map.exit() == Some(pt) {
+                'E'
+            } else if state.visits(pt) > 0 {
+                'o'
+            } else {
+                '.'
+            };
+            out.push(char);
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn run() -> Result<(), String> {
+    let args = parse_args()?;
+
+    let map_str = std::fs::read_to_string(&args.map_path)
+        .map_err(|err| format!("Could not read map file '{}': {err}", args.map_path))?;
+    let rules_str = std::fs::read_to_string(&args.rules_path)
+        .map_err(|err| format!("Could not read rules file '{}': {err}", args.rules_path))?;
+
+    let map = Map::parse(&map_str)
+        .and_then(|map| map.check_enclosed().map(|_| map))
+        .map_err(|err| format!("Invalid map file '{}': {err}", args.map_path))?;
+    let program = dsl::parse(&rules_str).map_err(|err| {
+        format!(
+            "Invalid rules file '{}', {}",
+            args.rules_path,
+            err.render(&rules_str)
+        )
+    })?;
+
+    // the map's own physics settings win over the flags
+    let physics = map.physics().apply(args.physics);
+    let report = runner::run(&map, &program, physics, args.tick_limit);
+
+    print!("{}", render(&map, &report));
+    println!();
+    println!("ticks:          {}", report.ticks());
+    println!("bumps:          {}", report.bumps);
+    println!("coverage:       {:.1}%", report.coverage(&map) * 100.);
+    println!(
+        "dirt remaining: {}/{}",
+        report.final_state.remaining_dirt(&map),
+        map.dirt().len()
+    );
+    println!("looped:         {}", report.looped);
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,30 +1,126 @@
 use bevy_egui::egui::WidgetText;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::core::{
+    dsl::is_valid_mode_name,
+    register::{Counter, Flag},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Command {
     TurnRight,
     TurnLeft,
+    TurnAround,
+
+    /// backs up one tile, keeping the same heading
+    MoveBackward,
+    /// stays put for the tick
+    Wait,
+
+    /// cleans the tile in front of the vac without moving onto it
+    Vacuum,
+    /// cleans the tiles to either side of the vac
+    Brush,
+
+    SetFlag(Flag),
+    ClearFlag(Flag),
+    Increment(Counter),
+
+    /// switches to the program's mode with the given name from the next tick on
+    GoToMode(String),
 
-    // FIXME: this shouldn't be a command as it isn't
-    // something the player can control
-    MoveForward,
+    Beep,
+}
+
+/// Commands are grouped into categories, and at most one command from each category fires
+/// in a tick. The order of the variants is the order in which the commands are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Rotation,
+    /// the program's default action falls in this category too
+    Movement,
+    Tool,
+    /// changes to flags and counters, which the sensors only pick up on the next tick
+    Register,
+    Transition,
+    Signal,
+}
+
+impl Command {
+    /// the commands that rules can fire, with an empty mode name for `GoToMode`
+    pub const PLAYER: [Command; 19] = [
+        Command::TurnRight,
+        Command::TurnLeft,
+        Command::TurnAround,
+        Command::MoveBackward,
+        Command::Wait,
+        Command::Vacuum,
+        Command::Brush,
+        Command::SetFlag(Flag::A),
+        Command::SetFlag(Flag::B),
+        Command::SetFlag(Flag::C),
+        Command::SetFlag(Flag::D),
+        Command::ClearFlag(Flag::A),
+        Command::ClearFlag(Flag::B),
+        Command::ClearFlag(Flag::C),
+        Command::ClearFlag(Flag::D),
+        Command::Increment(Counter::X),
+        Command::Increment(Counter::Y),
+        Command::GoToMode(String::new()),
+        Command::Beep,
+    ];
+
+    /// parses the part of a rule after "THEN", eg. "turn left" or "go to mode search"
+    pub fn parse(str: &str) -> Option<Command> {
+        let str = str.trim();
+
+        if let Some(mode) = str.strip_prefix("go to mode ") {
+            let mode = mode.trim();
+            return is_valid_mode_name(mode).then(|| Command::GoToMode(mode.into()));
+        }
+
+        Command::PLAYER
+            .into_iter()
+            .find(|command| command.to_string() == format!("THEN {str}"))
+    }
+
+    pub fn category(&self) -> Category {
+        match self {
+            Command::TurnRight | Command::TurnLeft | Command::TurnAround => Category::Rotation,
+            Command::MoveBackward | Command::Wait => Category::Movement,
+            Command::Vacuum | Command::Brush => Category::Tool,
+            Command::SetFlag(_) | Command::ClearFlag(_) | Command::Increment(_) => {
+                Category::Register
+            }
+            Command::GoToMode(_) => Category::Transition,
+            Command::Beep => Category::Signal,
+        }
+    }
 }
 
 // FIXME: should this live somewhere else? it isn't purely about game logic
 impl From<Command> for String {
     fn from(val: Command) -> Self {
-        match val {
-            Command::TurnRight => "THEN turn right",
-            Command::TurnLeft => "THEN turn left",
-            Command::MoveForward => "WHEN go forward",
-        }
-        .into()
+        val.to_string()
     }
 }
 
 impl std::fmt::Display for Command {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", String::from(*self))
+        match self {
+            Command::TurnRight => write!(f, "THEN turn right"),
+            Command::TurnLeft => write!(f, "THEN turn left"),
+            Command::TurnAround => write!(f, "THEN turn around"),
+            Command::MoveBackward => write!(f, "THEN move backward"),
+            Command::Wait => write!(f, "THEN wait"),
+            Command::Vacuum => write!(f, "THEN vacuum ahead"),
+            Command::Brush => write!(f, "THEN brush sides"),
+            Command::SetFlag(flag) => write!(f, "THEN set flag {}", flag.name()),
+            Command::ClearFlag(flag) => write!(f, "THEN clear flag {}", flag.name()),
+            Command::Increment(counter) => write!(f, "THEN increment counter {}", counter.name()),
+            Command::GoToMode(mode) => write!(f, "THEN go to mode {mode}"),
+            Command::Beep => write!(f, "THEN beep"),
+        }
     }
 }
 
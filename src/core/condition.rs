@@ -0,0 +1,427 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    register::Counter,
+    sensor::{Readings, Sensor},
+};
+
+/// When a rule fires, built up from sensors combined with AND, OR and NOT
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    Sensor(Sensor),
+    /// all of the conditions hold, which is trivially true when there are none
+    And(Vec<Condition>),
+    /// any of the conditions holds, which is never true when there are none
+    Or(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    /// whether the condition holds given what the vac currently senses
+    pub fn matches(&self, readings: &Readings) -> bool {
+        match self {
+            Condition::Sensor(sensor) => readings.is_active(*sensor),
+            Condition::And(conditions) => conditions.iter().all(|c| c.matches(readings)),
+            Condition::Or(conditions) => conditions.iter().any(|c| c.matches(readings)),
+            Condition::Not(condition) => !condition.matches(readings),
+        }
+    }
+
+    /// Parses a condition written the way `Display` writes it, eg. "space left AND NOT hit
+    /// wall". NOT binds tighter than AND, which binds tighter than OR, and parentheses can
+    /// be used for grouping.
+    pub fn parse(str: &str) -> Option<Condition> {
+        Self::parse_spanned(str).ok()
+    }
+
+    /// like `parse`, but on failure says what went wrong and where in `str`
+    pub(crate) fn parse_spanned(str: &str) -> Result<Condition, SpannedError> {
+        let tokens = tokenize(str);
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+            end: str.trim_end().len(),
+        };
+
+        let condition = parser.parse_or()?;
+        if parser.pos < tokens.len() {
+            return Err(parser.error("AND, OR or the end of the condition"));
+        }
+        Ok(condition)
+    }
+
+    /// whether the condition needs parentheses when it's an operand of another condition
+    fn is_compound(&self) -> bool {
+        match self {
+            Condition::And(conditions) | Condition::Or(conditions) => match conditions.as_slice() {
+                [] => false,
+                [condition] => condition.is_compound(),
+                _ => true,
+            },
+            Condition::Sensor(_) | Condition::Not(_) => false,
+        }
+    }
+}
+
+impl From<Sensor> for Condition {
+    fn from(sensor: Sensor) -> Self {
+        Condition::Sensor(sensor)
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // nested ANDs and ORs are always wrapped in parentheses, so the grouping survives
+        // being parsed back
+        let write_operand = |f: &mut std::fmt::Formatter<'_>, condition: &Condition| {
+            if condition.is_compound() {
+                write!(f, "({condition})")
+            } else {
+                write!(f, "{condition}")
+            }
+        };
+
+        let (conditions, separator, empty) = match self {
+            Condition::Sensor(sensor) => return write!(f, "{}", sensor.name()),
+            Condition::Not(condition) => {
+                write!(f, "NOT ")?;
+                return write_operand(f, condition);
+            }
+            Condition::And(conditions) => (conditions, " AND ", "always"),
+            Condition::Or(conditions) => (conditions, " OR ", "never"),
+        };
+
+        // an AND or OR of a single condition, which the editor builds along the way, is
+        // written as just that condition
+        match conditions.as_slice() {
+            [] => return write!(f, "{empty}"),
+            [condition] => return write!(f, "{condition}"),
+            _ => {}
+        }
+
+        for (idx, condition) in conditions.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "{separator}")?;
+            }
+            write_operand(f, condition)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What went wrong while parsing some text, along with the byte range it's about
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SpannedError {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+/// A word or parenthesis, along with where it starts in the text being parsed
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    start: usize,
+    text: &'a str,
+}
+
+fn tokenize(str: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+
+    for word in str.split_whitespace() {
+        let mut start = word.as_ptr() as usize - str.as_ptr() as usize;
+        let mut rest = word;
+        while !rest.is_empty() {
+            let end = rest.find(['(', ')']).unwrap_or(rest.len()).max(1);
+            tokens.push(Token {
+                start,
+                text: &rest[..end],
+            });
+            start += end;
+            rest = &rest[end..];
+        }
+    }
+
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token<'a>],
+    pos: usize,
+    /// length of the text being parsed, where errors at the end point to
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|token| token.text)
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let matched = self.peek() == Some(token);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    /// an error about the current token, or the end of the text if there are none left
+    fn error(&self, expected: &str) -> SpannedError {
+        match self.tokens.get(self.pos) {
+            Some(token) => SpannedError {
+                span: token.start..token.start + token.text.len(),
+                message: format!("Expected {expected}, found '{}'", token.text),
+            },
+            None => SpannedError {
+                span: self.end..self.end,
+                message: format!("Expected {expected}, found the end of the condition"),
+            },
+        }
+    }
+
+    /// an error covering the words from the current token up to the next operator, which
+    /// would have to make up a sensor name
+    fn unknown_sensor(&self) -> SpannedError {
+        let words = self.tokens[self.pos..]
+            .iter()
+            .take_while(|token| !["AND", "OR", "NOT", "(", ")"].contains(&token.text))
+            .collect::<Vec<_>>();
+
+        let (Some(first), Some(last)) = (words.first(), words.last()) else {
+            return self.error("a sensor");
+        };
+        let span = first.start..last.start + last.text.len();
+        let name = words.iter().map(|token| token.text).collect::<Vec<_>>();
+        SpannedError {
+            span,
+            message: format!("Unknown sensor '{}'", name.join(" ")),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Condition, SpannedError> {
+        self.parse_list("OR", Self::parse_and, Condition::Or)
+    }
+
+    fn parse_and(&mut self) -> Result<Condition, SpannedError> {
+        self.parse_list("AND", Self::parse_unary, Condition::And)
+    }
+
+    /// one or more operands separated by `operator`, collapsing down to the operand itself
+    /// when there's only one
+    fn parse_list(
+        &mut self,
+        operator: &str,
+        parse_operand: fn(&mut Self) -> Result<Condition, SpannedError>,
+        combine: fn(Vec<Condition>) -> Condition,
+    ) -> Result<Condition, SpannedError> {
+        let mut conditions = vec![parse_operand(self)?];
+        while self.eat(operator) {
+            conditions.push(parse_operand(self)?);
+        }
+
+        if conditions.len() == 1 {
+            Ok(conditions.pop().unwrap())
+        } else {
+            Ok(combine(conditions))
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Condition, SpannedError> {
+        if self.eat("NOT") {
+            return Ok(Condition::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.eat("(") {
+            let condition = self.parse_or()?;
+            if !self.eat(")") {
+                return Err(self.error("AND, OR or ')'"));
+            }
+            return Ok(condition);
+        }
+
+        if self.eat("always") {
+            return Ok(Condition::And(Vec::new()));
+        }
+        if self.eat("never") {
+            return Ok(Condition::Or(Vec::new()));
+        }
+
+        if let Some(sensor) = self.parse_counter_above() {
+            return Ok(Condition::Sensor(sensor));
+        }
+
+        // sensor names are made up of several words
+        let sensor = Sensor::ALL.into_iter().find(|sensor| {
+            let name = sensor.name();
+            let words = name.split(' ');
+            let len = words.clone().count();
+            self.tokens
+                .get(self.pos..self.pos + len)
+                .is_some_and(|tokens| tokens.iter().map(|token| token.text).eq(words))
+        });
+        let Some(sensor) = sensor else {
+            return Err(self.unknown_sensor());
+        };
+        self.pos += sensor.name().split(' ').count();
+
+        Ok(Condition::Sensor(sensor))
+    }
+
+    /// "counter x above 3", which can't be matched against a fixed name like other sensors
+    fn parse_counter_above(&mut self) -> Option<Sensor> {
+        let [keyword, name, above, value] = self.tokens.get(self.pos..self.pos + 4)? else {
+            return None;
+        };
+        if keyword.text != "counter" || above.text != "above" {
+            return None;
+        }
+
+        let counter = Counter::ALL
+            .into_iter()
+            .find(|counter| counter.name() == name.text)?;
+        let value = value.text.parse().ok()?;
+
+        self.pos += 4;
+        Some(Sensor::CounterAbove(counter, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::register::Flag;
+
+    use super::*;
+
+    #[test]
+    fn matches() {
+        let condition = Condition::And(vec![
+            Sensor::SpaceLeft.into(),
+            Condition::Not(Box::new(Sensor::HitWall.into())),
+        ]);
+
+        assert!(condition.matches(&Readings::new([Sensor::SpaceLeft])));
+        assert!(condition.matches(&Readings::new([Sensor::SpaceLeft, Sensor::SpaceRight])));
+        assert!(!condition.matches(&Readings::new([Sensor::SpaceLeft, Sensor::HitWall])));
+        assert!(!condition.matches(&Readings::new([])));
+
+        let condition = Condition::Or(vec![Sensor::SpaceLeft.into(), Sensor::SpaceRight.into()]);
+        assert!(condition.matches(&Readings::new([Sensor::SpaceRight])));
+        assert!(!condition.matches(&Readings::new([Sensor::HitWall])));
+
+        assert!(Condition::And(Vec::new()).matches(&Readings::new([])));
+        assert!(!Condition::Or(Vec::new()).matches(&Readings::new([Sensor::HitWall])));
+
+        let condition = Condition::Sensor(Sensor::CounterAbove(Counter::X, 2));
+        assert!(!condition.matches(&Readings::new([]).with_counter(Counter::X, 2)));
+        assert!(condition.matches(&Readings::new([]).with_counter(Counter::X, 3)));
+        assert!(!condition.matches(&Readings::new([]).with_counter(Counter::Y, 3)));
+    }
+
+    #[test]
+    fn display() {
+        let condition = Condition::Or(vec![
+            Condition::And(vec![
+                Sensor::SpaceLeft.into(),
+                Condition::Not(Box::new(Sensor::HitWall.into())),
+            ]),
+            Condition::Not(Box::new(Condition::Or(vec![
+                Sensor::SpaceRight.into(),
+                Condition::And(Vec::new()),
+            ]))),
+        ]);
+
+        assert_eq!(
+            condition.to_string(),
+            "(space left AND NOT hit wall) OR NOT (space right OR always)"
+        );
+
+        // a single condition in an AND or OR is written on its own, with the parentheses
+        // that it would need itself
+        let condition = Condition::And(vec![
+            Condition::Or(vec![Condition::Or(vec![
+                Sensor::SpaceLeft.into(),
+                Sensor::SpaceRight.into(),
+            ])]),
+            Condition::Not(Box::new(Condition::And(vec![Sensor::HitWall.into()]))),
+        ]);
+        assert_eq!(
+            condition.to_string(),
+            "(space left OR space right) AND NOT hit wall"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Condition::parse("hit wall"),
+            Some(Condition::Sensor(Sensor::HitWall))
+        );
+
+        // NOT binds tighter than AND, which binds tighter than OR
+        assert_eq!(
+            Condition::parse("NOT hit wall AND space left OR space right"),
+            Some(Condition::Or(vec![
+                Condition::And(vec![
+                    Condition::Not(Box::new(Sensor::HitWall.into())),
+                    Sensor::SpaceLeft.into(),
+                ]),
+                Sensor::SpaceRight.into(),
+            ]))
+        );
+
+        assert_eq!(
+            Condition::parse("NOT(space left OR space right)"),
+            Some(Condition::Not(Box::new(Condition::Or(vec![
+                Sensor::SpaceLeft.into(),
+                Sensor::SpaceRight.into(),
+            ]))))
+        );
+
+        assert_eq!(
+            Condition::parse("flag b set AND NOT counter y above 12"),
+            Some(Condition::And(vec![
+                Sensor::FlagSet(Flag::B).into(),
+                Condition::Not(Box::new(Sensor::CounterAbove(Counter::Y, 12).into())),
+            ]))
+        );
+
+        assert_eq!(Condition::parse(""), None);
+        assert_eq!(Condition::parse("hit"), None);
+        assert_eq!(Condition::parse("(hit wall"), None);
+        assert_eq!(Condition::parse("hit wall AND"), None);
+        assert_eq!(Condition::parse("hit wall space left"), None);
+        assert_eq!(Condition::parse("counter z above 1"), None);
+        assert_eq!(Condition::parse("counter x above -1"), None);
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let conditions = [
+            Condition::And(vec![
+                Condition::And(vec![Sensor::HitWall.into(), Sensor::SpaceLeft.into()]),
+                Sensor::SpaceRight.into(),
+            ]),
+            Condition::Not(Box::new(Condition::Not(Box::new(Sensor::HitWall.into())))),
+            Condition::Or(vec![Condition::Or(Vec::new()), Condition::And(Vec::new())]),
+        ];
+
+        for condition in conditions {
+            assert_eq!(Condition::parse(&condition.to_string()), Some(condition));
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = Condition::parse_spanned("space left AND hit wal").unwrap_err();
+        assert_eq!(err.span, 15..22);
+        assert_eq!(err.message, "Unknown sensor 'hit wal'");
+
+        let err = Condition::parse_spanned("(hit wall").unwrap_err();
+        assert_eq!(err.span, 9..9);
+        assert!(err.message.contains("')'"));
+
+        let err = Condition::parse_spanned("hit wall)").unwrap_err();
+        assert_eq!(err.span, 8..9);
+    }
+}
@@ -2,7 +2,7 @@ use std::f32::consts::PI;
 
 use bevy::math::{IVec2, Vec2};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Dir {
     East,
     North,
@@ -29,6 +29,10 @@ impl Dir {
         }
     }
 
+    pub fn reverse(self) -> Self {
+        self.rotate_cw().rotate_cw()
+    }
+
     pub fn to_radians(self) -> f32 {
         match self {
             Dir::East => 0.,
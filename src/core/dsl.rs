@@ -0,0 +1,427 @@
+//! The text format programs are written in, the same way the rule editor shows them:
+//!
+//! ```text
+//! // follow the right wall, looking for dirt
+//! WHEN space right AND NOT visited right THEN turn right
+//! WHEN hit wall THEN turn left  // trailing comments work too
+//! DISABLED WHEN dirt ahead THEN vacuum ahead
+//! OTHERWISE go forward
+//!
+//! MODE search
+//! WHEN hit wall THEN go to mode main
+//! ```
+//!
+//! Rules before the first `MODE` line belong to the main mode. Rules can switch to a mode
+//! that doesn't exist, which does nothing, the same as after the mode is deleted in the
+//! editor.
+
+use std::{fmt::Write, ops::Range};
+
+use crate::core::{
+    command::Command,
+    condition::{Condition, SpannedError},
+    program::{DefaultAction, Mode, Program, check_mode_name},
+    rule::Rule,
+};
+
+const COMMENT: &str = "//";
+const DISABLED: &str = "DISABLED ";
+
+/// words of the text format that can't be used as mode names
+const KEYWORDS: [&str; 8] = [
+    "WHEN",
+    "THEN",
+    "MODE",
+    "OTHERWISE",
+    "DISABLED",
+    "AND",
+    "OR",
+    "NOT",
+];
+
+/// mode names are single words made of letters, digits, `_` and `-`, so they can't be
+/// confused with the rest of a rule or with a comment
+pub fn is_valid_mode_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|char| char.is_alphanumeric() || char == '_' || char == '-')
+        && !KEYWORDS.contains(&name)
+}
+
+/// What's wrong with a program's text, and where
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// 1-based, like a text editor shows them
+    pub line: usize,
+    /// 1-based and counted in characters, like a text editor shows them
+    pub column: usize,
+    /// byte range within the line
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl ParseError {
+    /// an error about `span` of `text`, the line with the given number
+    fn new(line: usize, text: &str, span: Range<usize>, message: impl Into<String>) -> Self {
+        let column = text
+            .get(..span.start)
+            .map_or(span.start, |before| before.chars().count())
+            + 1;
+
+        Self {
+            line,
+            column,
+            span,
+            message: message.into(),
+        }
+    }
+
+    /// the error along with the offending line, with the span underlined
+    pub fn render(&self, source: &str) -> String {
+        let line = source.lines().nth(self.line - 1).unwrap_or_default();
+        let width = line
+            .get(self.span.clone())
+            .map_or(0, |span| span.chars().count())
+            .max(1);
+
+        format!(
+            "{self}\n  | {line}\n  | {}{}",
+            " ".repeat(self.column - 1),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a whole program. Blank lines and anything after `//` are ignored.
+pub fn parse(str: &str) -> Result<Program, ParseError> {
+    let mut modes: Vec<Mode> = Vec::new();
+    let mut default_action = None;
+
+    for (idx, line) in str.lines().enumerate() {
+        let line_number = idx + 1;
+        let code = line.split_once(COMMENT).map_or(line, |(code, _)| code);
+        let text = code.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let offset = code.len() - code.trim_start().len();
+        let error = |span: Range<usize>, message: String| {
+            ParseError::new(
+                line_number,
+                line,
+                span.start + offset..span.end + offset,
+                message,
+            )
+        };
+
+        if let Some(name) = text.strip_prefix("MODE ") {
+            let name = name.trim();
+            let span = text.len() - name.len()..text.len();
+            check_mode_name(&modes, name).map_err(|message| error(span, message))?;
+
+            modes.push(Mode::new(name, []));
+            continue;
+        }
+
+        if text.starts_with("OTHERWISE") {
+            // spacing doesn't matter between words
+            let words = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            let Some(action) = DefaultAction::ALL
+                .into_iter()
+                .find(|action| words == String::from(*action))
+            else {
+                return Err(error(
+                    0..text.len(),
+                    format!("Unknown default action '{text}'"),
+                ));
+            };
+
+            if let Some((_, first)) = default_action {
+                return Err(error(
+                    0..text.len(),
+                    format!("Default action was already set on line {first}"),
+                ));
+            }
+            default_action = Some((action, line_number));
+            continue;
+        }
+
+        let rule = parse_rule(text).map_err(|err| error(err.span, err.message))?;
+
+        if modes.is_empty() {
+            modes.push(Mode::new(Program::MAIN_MODE, []));
+        }
+        modes.last_mut().unwrap().rules_mut().push(rule);
+    }
+
+    if modes.is_empty() {
+        modes.push(Mode::new(Program::MAIN_MODE, []));
+    }
+
+    let default_action = default_action.map(|(action, _)| action).unwrap_or_default();
+    Ok(Program::with_modes(modes).with_default_action(default_action))
+}
+
+/// Parses a single rule, eg. "WHEN hit wall THEN turn left", with the error span relative
+/// to `text`
+pub(crate) fn parse_rule(text: &str) -> Result<Rule, SpannedError> {
+    let error = |span: Range<usize>, message: String| SpannedError { span, message };
+
+    let (enabled, rest) = match text.strip_prefix(DISABLED) {
+        Some(rest) => (false, rest.trim_start()),
+        None => (true, text),
+    };
+    let rest_start = text.len() - rest.len();
+
+    let Some(condition_text) = rest.strip_prefix("WHEN ") else {
+        let word = rest.split_whitespace().next().unwrap_or_default();
+        return Err(error(
+            rest_start..rest_start + word.len(),
+            format!("Expected WHEN, MODE or OTHERWISE, found '{word}'"),
+        ));
+    };
+    let condition_start = text.len() - condition_text.len();
+
+    // the last THEN that's a word of its own splits the condition from the command
+    let then = condition_text.rmatch_indices("THEN").find(|(idx, _)| {
+        let before = &condition_text[..*idx];
+        let after = &condition_text[idx + 4..];
+        before.ends_with(' ') && (after.is_empty() || after.starts_with(char::is_whitespace))
+    });
+    let Some((then, _)) = then else {
+        return Err(error(
+            text.len()..text.len(),
+            "Expected THEN followed by a command".into(),
+        ));
+    };
+
+    let condition = Condition::parse_spanned(&condition_text[..then]).map_err(|err| {
+        error(
+            err.span.start + condition_start..err.span.end + condition_start,
+            err.message,
+        )
+    })?;
+
+    let command_text = condition_text[then + 4..].trim();
+    if command_text.is_empty() {
+        return Err(error(
+            text.len()..text.len(),
+            "Expected a command after THEN".into(),
+        ));
+    }
+    let Some(command) = Command::parse(command_text) else {
+        return Err(error(
+            text.len() - command_text.len()..text.len(),
+            format!("Unknown command '{command_text}'"),
+        ));
+    };
+
+    let mut rule = Rule::with_condition(condition, command);
+    rule.set_enabled(enabled);
+    Ok(rule)
+}
+
+/// Writes a program out in the canonical form of the text format, which `parse` reads back
+/// in as the same program. The one exception is an AND or OR of a single condition, which
+/// the condition editor builds along the way and which is written as just that condition,
+/// so it comes back without the AND or OR around it but behaves the same.
+pub fn print(program: &Program) -> String {
+    let mut out = String::new();
+
+    // a program that's just the main mode doesn't need the header
+    let headers = !matches!(program.modes(), [mode] if mode.name() == Program::MAIN_MODE);
+
+    for (idx, mode) in program.modes().iter().enumerate() {
+        if headers {
+            if idx > 0 {
+                out.push('\n');
+            }
+            writeln!(out, "MODE {}", mode.name()).unwrap();
+        }
+
+        for rule in mode.rules() {
+            if !rule.is_enabled() {
+                out.push_str(DISABLED);
+            }
+            writeln!(out, "{rule}").unwrap();
+        }
+    }
+
+    if program.default_action() != DefaultAction::default() {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        writeln!(out, "{}", program.default_action()).unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::sensor::Sensor;
+
+    use super::*;
+
+    #[test]
+    fn parse_comments() {
+        let program = parse(
+            r"// a comment on its own line
+  WHEN hit wall THEN turn left // and one after a rule
+
+DISABLED WHEN space right THEN turn right",
+        )
+        .unwrap();
+
+        let rules = program.modes()[0].rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].condition(), &Condition::Sensor(Sensor::HitWall));
+        assert!(rules[0].is_enabled());
+        assert!(!rules[1].is_enabled());
+    }
+
+    #[test]
+    fn parse_error_spans() {
+        let error = |str| parse(str).unwrap_err();
+
+        let err = error("WHEN hit wall THEN turn left\n  WHEN hit wal THEN turn left");
+        assert_eq!((err.line, err.span.clone()), (2, 7..14));
+        assert_eq!(
+            err.to_string(),
+            "line 2, column 8: Unknown sensor 'hit wal'"
+        );
+
+        let err = error("WHEN (hit wall THEN turn left");
+        assert_eq!(err.span, 14..14);
+
+        let err = error("WHEN hit wall THEN fly");
+        assert_eq!(err.span, 19..22);
+        assert!(err.message.contains("'fly'"));
+
+        let err = error("WHEN hit wall THEN");
+        assert_eq!(err.span, 18..18);
+
+        let err = error("WHEN hit wall turn left");
+        assert!(err.message.contains("THEN"));
+
+        let err = error("IF hit wall THEN turn left");
+        assert_eq!(err.span, 0..2);
+
+        let err = error("MODE a\nMODE  a");
+        assert_eq!((err.line, err.span), (2, 6..7));
+
+        let err = error("OTHERWISE wait\nOTHERWISE fly");
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn render_error() {
+        let source = "WHEN hit wall THEN fly";
+        let err = parse(source).unwrap_err();
+        assert_eq!(
+            err.render(source),
+            "line 1, column 20: Unknown command 'fly'\n  | WHEN hit wall THEN fly\n  |                    ^^^"
+        );
+
+        // columns count characters rather than bytes, here two-byte non-breaking spaces
+        let source = "\u{a0}\u{a0}WHEN hit wal THEN turn left";
+        let err = parse(source).unwrap_err();
+        assert_eq!((err.span.clone(), err.column), (9..16, 8));
+        assert_eq!(
+            err.render(source),
+            format!(
+                "line 1, column 8: Unknown sensor 'hit wal'\n  | {source}\n  | {}^^^^^^^",
+                " ".repeat(7)
+            )
+        );
+    }
+
+    #[test]
+    fn print_canonical() {
+        let program = parse(
+            r"OTHERWISE  wait
+WHEN (hit wall)   THEN go to mode search // comment
+MODE search
+DISABLED   WHEN NOT(space left OR space right) THEN beep",
+        )
+        .unwrap();
+
+        let printed = print(&program);
+        assert_eq!(
+            printed,
+            r"MODE main
+WHEN hit wall THEN go to mode search
+
+MODE search
+DISABLED WHEN NOT (space left OR space right) THEN beep
+
+OTHERWISE wait
+"
+        );
+        assert_eq!(parse(&printed), Ok(program));
+
+        let program = parse("WHEN hit wall THEN turn left").unwrap();
+        assert_eq!(print(&program), "WHEN hit wall THEN turn left\n");
+        assert_eq!(print(&Program::default()), "");
+    }
+
+    #[test]
+    fn print_editor_programs() {
+        // a rule left switching to a mode that was deleted in the editor
+        let mut program = Program::default();
+        let idx = program.add_mode();
+        program.mode_mut(0).unwrap().rules_mut().push(Rule::new(
+            Sensor::HitWall,
+            Command::GoToMode("mode2".into()),
+        ));
+        program.remove_mode(idx);
+        assert_eq!(parse(&print(&program)), Ok(program));
+
+        // the condition editor wraps a sensor in an AND before the second operand is added
+        let rule = Rule::with_condition(
+            Condition::Or(vec![
+                Condition::And(vec![Sensor::SpaceLeft.into()]),
+                Condition::Not(Box::new(Condition::Or(vec![Sensor::HitWall.into()]))),
+            ]),
+            Command::TurnLeft,
+        );
+        let program = Program::new([rule]);
+        let printed = print(&program);
+        assert_eq!(printed, "WHEN space left OR NOT hit wall THEN turn left\n");
+
+        let expected = Condition::Or(vec![
+            Sensor::SpaceLeft.into(),
+            Condition::Not(Box::new(Sensor::HitWall.into())),
+        ]);
+        let parsed = parse(&printed).unwrap();
+        assert_eq!(parsed.modes()[0].rules()[0].condition(), &expected);
+    }
+
+    #[test]
+    fn mode_names_are_words() {
+        // `//` starts a comment, so a name with it in wouldn't be read back in full
+        assert!(!is_valid_mode_name("a//b"));
+        assert_eq!(parse("MODE a//b").unwrap().mode_idx("a"), Some(0));
+
+        // nor can a keyword be one, as it'd be mistaken for the one that splits up a rule
+        assert!(!is_valid_mode_name("THEN"));
+        assert!(parse("MODE THEN").is_err());
+        assert!(parse("WHEN hit wall THEN go to mode THEN").is_err());
+
+        let program = parse("MODE search-2_b\nWHEN hit wall THEN go to mode search-2_b").unwrap();
+        assert_eq!(parse(&print(&program)), Ok(program));
+    }
+}
@@ -0,0 +1,172 @@
+/// Undo and redo stacks of earlier versions of something being edited, each labelled with
+/// the edit that replaced it
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    undo: Vec<(String, T)>,
+    redo: Vec<(String, T)>,
+    limit: usize,
+    /// whether `record_merged` may fold into the last edit
+    merging: bool,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_LIMIT)
+    }
+}
+
+impl<T> History<T> {
+    pub const DEFAULT_LIMIT: usize = 100;
+
+    /// a history that remembers up to `limit` edits, forgetting the oldest ones first
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit,
+            merging: false,
+        }
+    }
+
+    /// records an edit, given the version from before it was made
+    pub fn record(&mut self, label: impl Into<String>, before: T) {
+        self.merging = false;
+        self.redo.clear();
+        self.undo.push((label.into(), before));
+
+        if self.undo.len() > self.limit {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Like `record`, except that an edit with the same label as the last one is folded
+    /// into it, so a burst of small changes (eg. dragging a number) is undone in one go.
+    /// The burst ends with any other edit, an undo or redo, or `stop_merging`.
+    pub fn record_merged(&mut self, label: impl Into<String>, before: T) {
+        let label = label.into();
+        let same_as_last = self.merging
+            && self
+                .undo
+                .last()
+                .is_some_and(|(last_label, _)| *last_label == label);
+
+        if !same_as_last {
+            self.record(label, before);
+        }
+        self.merging = true;
+    }
+
+    /// makes the next `record_merged` start an edit of its own, even with the same label
+    pub fn stop_merging(&mut self) {
+        self.merging = false;
+    }
+
+    /// swaps `current` for the version from before the last edit
+    pub fn undo(&mut self, current: T) -> Result<T, T> {
+        self.merging = false;
+        let Some((label, before)) = self.undo.pop() else {
+            return Err(current);
+        };
+
+        self.redo.push((label, current));
+        Ok(before)
+    }
+
+    /// swaps `current` for the version from after the last undone edit
+    pub fn redo(&mut self, current: T) -> Result<T, T> {
+        self.merging = false;
+        let Some((label, after)) = self.redo.pop() else {
+            return Err(current);
+        };
+
+        self.undo.push((label, current));
+        Ok(after)
+    }
+
+    /// label of the edit that `undo` would revert
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.last().map(|(label, _)| label.as_str())
+    }
+
+    /// label of the edit that `redo` would make again
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|(label, _)| label.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.merging = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_redo() {
+        let mut history = History::default();
+        let mut value = 1;
+
+        history.record("add one", value);
+        value += 1;
+        history.record("double", value);
+        value *= 2;
+        assert_eq!(history.undo_label(), Some("double"));
+
+        value = history.undo(value).unwrap();
+        assert_eq!(value, 2);
+        value = history.undo(value).unwrap();
+        assert_eq!(value, 1);
+        assert_eq!(history.undo(value), Err(1));
+
+        assert_eq!(history.redo_label(), Some("add one"));
+        value = history.redo(value).unwrap();
+        assert_eq!(value, 2);
+
+        // a new edit drops whatever could have been redone
+        history.record("negate", value);
+        value = -value;
+        assert_eq!(history.redo(value), Err(-2));
+        assert_eq!(history.undo(value), Ok(2));
+    }
+
+    #[test]
+    fn record_merged() {
+        let mut history = History::default();
+
+        history.record_merged("drag", 1);
+        history.record_merged("drag", 2);
+        history.record_merged("drag", 3);
+        assert_eq!(history.undo(4), Ok(1));
+
+        // an undone edit isn't merged into
+        history.record_merged("drag", 1);
+        assert_eq!(history.undo(2), Ok(1));
+        history.record_merged("drag", 1);
+        assert_eq!(history.undo(2), Ok(1));
+        assert_eq!(history.undo(1), Err(1));
+
+        // nor is one from a burst that's over, like an earlier session editing the same rule
+        history.record_merged("edit rule 1", 1);
+        history.record_merged("edit rule 1", 2);
+        history.stop_merging();
+        history.record_merged("edit rule 1", 3);
+        history.record_merged("edit rule 1", 4);
+        assert_eq!(history.undo(5), Ok(3));
+        assert_eq!(history.undo(3), Ok(1));
+    }
+
+    #[test]
+    fn limit() {
+        let mut history = History::new(2);
+        for value in 0..5 {
+            history.record("set", value);
+        }
+
+        assert_eq!(history.undo(5), Ok(4));
+        assert_eq!(history.undo(4), Ok(3));
+        assert_eq!(history.undo(3), Err(3));
+    }
+}
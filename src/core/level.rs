@@ -0,0 +1,290 @@
+use crate::core::{
+    map::Map,
+    physics::Physics,
+    state::{Effect, State},
+};
+
+/// What the player has to achieve to complete a level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Goal {
+    /// clean every dirty tile on the map
+    CleanAll,
+    /// visit every floor tile on the map
+    CoverFloor,
+    /// drive onto the map's exit tile
+    ReachExit,
+    /// clean at least this many dirty tiles
+    CleanTiles(usize),
+}
+
+impl Goal {
+    /// picks a goal for a map that doesn't come with one: reach the exit if there is one,
+    /// otherwise clean up the dirt, otherwise cover the floor
+    pub fn default_for(map: &Map) -> Self {
+        if map.exit().is_some() {
+            Goal::ReachExit
+        } else if !map.dirt().is_empty() {
+            Goal::CleanAll
+        } else {
+            Goal::CoverFloor
+        }
+    }
+}
+
+impl std::fmt::Display for Goal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Goal::CleanAll => write!(f, "Clean all dirt"),
+            Goal::CoverFloor => write!(f, "Cover the whole floor"),
+            Goal::ReachExit => write!(f, "Reach the exit"),
+            Goal::CleanTiles(n) => write!(f, "Clean {n} tiles"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Level {
+    map: Map,
+    goal: Goal,
+
+    /// max number of ticks the vac gets to reach the goal
+    tick_budget: Option<usize>,
+    physics: Physics,
+}
+
+impl Level {
+    pub fn new(map: Map, goal: Goal) -> Self {
+        Self {
+            map,
+            goal,
+            tick_budget: None,
+            physics: Physics::default(),
+        }
+    }
+
+    pub fn with_tick_budget(mut self, tick_budget: usize) -> Self {
+        self.tick_budget = Some(tick_budget);
+        self
+    }
+
+    pub fn with_physics(mut self, physics: Physics) -> Self {
+        self.physics = physics;
+        self
+    }
+
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    pub fn goal(&self) -> Goal {
+        self.goal
+    }
+
+    pub fn tick_budget(&self) -> Option<usize> {
+        self.tick_budget
+    }
+
+    pub fn physics(&self) -> Physics {
+        self.physics
+    }
+
+    /// checks whether the goal is met given the current state
+    fn goal_met(&self, state: &State) -> bool {
+        match self.goal {
+            Goal::CleanAll => state.remaining_dirt(&self.map) == 0,
+            Goal::CoverFloor => self.map.floor().all(|pt| state.visits(pt) > 0),
+            Goal::ReachExit => self.map.exit() == Some(state.vac_pos()),
+            Goal::CleanTiles(n) => self.map.dirt().len() - state.remaining_dirt(&self.map) >= n,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Score {
+    pub ticks: usize,
+    pub bumps: usize,
+    pub rule_count: usize,
+}
+
+impl Score {
+    const BASE_POINTS: usize = 1000;
+    const BUMP_PENALTY: usize = 5;
+    const RULE_PENALTY: usize = 20;
+
+    /// fewer ticks, bumps and rules all make for a higher score
+    pub fn points(&self) -> usize {
+        Self::BASE_POINTS.saturating_sub(
+            self.ticks + self.bumps * Self::BUMP_PENALTY + self.rule_count * Self::RULE_PENALTY,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LevelStatus {
+    InProgress,
+    Complete,
+    /// the tick budget ran out before the goal was met
+    Failed,
+}
+
+/// Tracks a single run of a level, fed with the effects returned by `State::tick`
+#[derive(Debug, Clone)]
+pub struct Progress {
+    score: Score,
+    status: LevelStatus,
+}
+
+impl Progress {
+    pub fn new(level: &Level, state: &State, rule_count: usize) -> Self {
+        let mut progress = Self {
+            score: Score {
+                rule_count,
+                ..Default::default()
+            },
+            status: LevelStatus::InProgress,
+        };
+
+        if level.goal_met(state) {
+            progress.status = LevelStatus::Complete;
+        }
+
+        progress
+    }
+
+    /// records the effects of one tick and returns the updated status
+    pub fn record(&mut self, level: &Level, state: &State, effects: &[Effect]) -> LevelStatus {
+        if self.status != LevelStatus::InProgress {
+            return self.status;
+        }
+
+        self.score.ticks += 1;
+
+        self.score.bumps += effects.iter().filter(|effect| effect.is_bump()).count();
+
+        if level.goal_met(state) {
+            self.status = LevelStatus::Complete;
+        } else if level
+            .tick_budget
+            .is_some_and(|budget| self.score.ticks >= budget)
+        {
+            self.status = LevelStatus::Failed;
+        }
+
+        self.status
+    }
+
+    /// the program can change between setting up a run and starting it
+    pub fn set_rule_count(&mut self, rule_count: usize) {
+        self.score.rule_count = rule_count;
+    }
+
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    pub fn status(&self) -> LevelStatus {
+        self.status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{command::Command, program::Program, rule::Rule, sensor::Sensor};
+
+    use super::*;
+
+    fn run(level: &Level, rules: &[Rule], ticks: usize) -> Progress {
+        let mut state = State::at_start(level.map());
+        let mut progress = Progress::new(level, &state, rules.len());
+        let program = Program::new(rules.to_vec());
+
+        for _ in 0..ticks {
+            let effects = state.tick(level.map(), &program, level.physics());
+            if progress.record(level, &state, &effects) != LevelStatus::InProgress {
+                break;
+            }
+        }
+
+        progress
+    }
+
+    #[test]
+    fn reach_exit() {
+        let level = Level::new(Map::parse(Map::CORRIDOR_5X3).unwrap(), Goal::ReachExit);
+
+        let progress = run(&level, &[], 10);
+        assert_eq!(progress.status(), LevelStatus::Complete);
+        assert_eq!(progress.score().ticks, 2);
+        assert_eq!(progress.score().bumps, 0);
+    }
+
+    #[test]
+    fn clean_all() {
+        let level = Level::new(Map::parse(Map::DIRTY_ROOM_4X4).unwrap(), Goal::CleanAll);
+        let rules = [Rule::new(Sensor::HitWall, Command::TurnLeft)];
+
+        let progress = run(&level, &rules, 20);
+        assert_eq!(progress.status(), LevelStatus::Complete);
+        assert_eq!(progress.score().rule_count, 1);
+        assert!(progress.score().bumps > 0);
+    }
+
+    #[test]
+    fn clean_tiles() {
+        let level = Level::new(
+            Map::parse(Map::DIRTY_ROOM_4X4).unwrap(),
+            Goal::CleanTiles(1),
+        );
+
+        let progress = run(&level, &[], 10);
+        assert_eq!(progress.status(), LevelStatus::Complete);
+        assert_eq!(progress.score().ticks, 1);
+    }
+
+    #[test]
+    fn cover_floor() {
+        let level = Level::new(Map::parse(Map::ROOM_4X4).unwrap(), Goal::CoverFloor);
+        let rules = [Rule::new(Sensor::HitWall, Command::TurnLeft)];
+
+        let progress = run(&level, &rules, 20);
+        assert_eq!(progress.status(), LevelStatus::Complete);
+        // it takes at least three moves to get onto each of the four floor tiles
+        assert!(progress.score().ticks >= 3);
+    }
+
+    #[test]
+    fn out_of_ticks() {
+        let level =
+            Level::new(Map::parse(Map::ROOM_4X4).unwrap(), Goal::CoverFloor).with_tick_budget(3);
+
+        // without any rules the vac just keeps bumping into the same wall
+        let progress = run(&level, &[], 20);
+        assert_eq!(progress.status(), LevelStatus::Failed);
+        assert_eq!(progress.score().ticks, 3);
+    }
+
+    #[test]
+    fn default_goal() {
+        let goal_for = |str| Goal::default_for(&Map::parse(str).unwrap());
+
+        assert_eq!(goal_for(Map::CORRIDOR_5X3), Goal::ReachExit);
+        assert_eq!(goal_for(Map::DIRTY_ROOM_4X4), Goal::CleanAll);
+        assert_eq!(goal_for(Map::ROOM_4X4), Goal::CoverFloor);
+    }
+
+    #[test]
+    fn score_points() {
+        let score = Score {
+            ticks: 100,
+            bumps: 2,
+            rule_count: 3,
+        };
+        assert_eq!(score.points(), 1000 - 100 - 10 - 60);
+
+        let score = Score {
+            ticks: 5000,
+            ..Default::default()
+        };
+        assert_eq!(score.points(), 0);
+    }
+}
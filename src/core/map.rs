@@ -1,59 +1,251 @@
 use bevy::math::IVec2;
 
-#[derive(Debug)]
+use crate::core::{dir::Dir, physics::PhysicsSettings};
+
+/// Problems with a map string. Rows and columns are 1-based, counting from the top left,
+/// so that they match what a text editor shows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapParseError {
+    Empty,
+    UnrecognizedChar {
+        char: char,
+        row: usize,
+        col: usize,
+    },
+    /// a row that isn't as wide as the first one
+    RaggedRow {
+        row: usize,
+        width: usize,
+        expected: usize,
+    },
+    /// no 'S' or heading marker ('>', '<', '^', 'v')
+    MissingStart,
+    DuplicateStart {
+        row: usize,
+        col: usize,
+    },
+    DuplicateExit {
+        row: usize,
+        col: usize,
+    },
+    /// a floor tile on the edge of the map
+    NotEnclosed {
+        row: usize,
+        col: usize,
+    },
+    /// a line below the map that isn't a physics setting
+    InvalidSetting {
+        row: usize,
+        line: String,
+    },
+}
+
+impl std::fmt::Display for MapParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "map is empty"),
+            Self::UnrecognizedChar { char, row, col } => {
+                write!(
+                    f,
+                    "row {row}, column {col}: unrecognized character '{char}'"
+                )
+            }
+            Self::RaggedRow {
+                row,
+                width,
+                expected,
+            } => write!(
+                f,
+                "row {row}: row is {width} tiles wide, but the first row is {expected} tiles wide"
+            ),
+            Self::MissingStart => {
+                write!(f, "map has no start position ('S', '>', '<', '^' or 'v')")
+            }
+            Self::DuplicateStart { row, col } => {
+                write!(
+                    f,
+                    "row {row}, column {col}: map has more than one start position"
+                )
+            }
+            Self::DuplicateExit { row, col } => {
+                write!(f, "row {row}, column {col}: map has more than one exit")
+            }
+            Self::NotEnclosed { row, col } => {
+                write!(f, "row {row}, column {col}: map isn't enclosed by walls")
+            }
+            Self::InvalidSetting { row, line } => {
+                write!(f, "row {row}: invalid setting '{line}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Floor,
+    Wall,
+    /// floor that starts out dirty
+    Dirt,
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Map {
+    /// every tile on the map, row by row starting from the bottom, so that lookups
+    /// don't have to search through the walls
+    tiles: Vec<Tile>,
+
+    // positions of the walls and dirt are also kept as lists for rendering
     walls: Vec<IVec2>,
+    dirt: Vec<IVec2>,
     start: IVec2,
+    start_dir: Dir,
+    exit: Option<IVec2>,
 
     width: usize,
     height: usize,
+
+    /// physics that the level played on the map sets for itself
+    physics: PhysicsSettings,
 }
 
 impl Default for Map {
     fn default() -> Self {
         Self {
+            tiles: vec![Tile::Floor; 25],
             walls: Vec::new(),
+            dirt: Vec::new(),
             start: (0, 0).into(),
+            start_dir: Dir::East,
+            exit: None,
             width: 5,
             height: 5,
+            physics: PhysicsSettings::default(),
         }
     }
 }
 
 impl Map {
-    pub fn parse(str: &str) -> Result<Self, String> {
+    /// Parses a map, which can be followed by a blank line and then physics settings, one
+    /// per line, eg. "turn cooldown: 2"
+    pub fn parse(str: &str) -> Result<Self, MapParseError> {
         let mut walls = Vec::new();
-        let mut start = (0, 0).into();
+        let mut dirt = Vec::new();
+        let mut start = None;
+        let mut exit = None;
+
+        let mut lines = str.lines();
+        let rows = lines
+            .by_ref()
+            .take_while(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+
+        let width = rows.first().ok_or(MapParseError::Empty)?.chars().count();
+        let height = rows.len();
 
-        let width = str.lines().next().ok_or("String is empty.")?.len();
-        let height = str.lines().count();
+        let mut tiles = vec![Tile::Floor; width * height];
+
+        for (row_idx, row) in rows.into_iter().enumerate() {
+            let row_width = row.chars().count();
+            if row_width != width {
+                return Err(MapParseError::RaggedRow {
+                    row: row_idx + 1,
+                    width: row_width,
+                    expected: width,
+                });
+            }
 
-        for (row_idx, row) in str.lines().enumerate() {
             for (col_idx, char) in row.chars().enumerate() {
+                let pt = IVec2::new(col_idx as i32, height as i32 - row_idx as i32 - 1);
+                let (row, col) = (row_idx + 1, col_idx + 1);
+                let idx = pt.y as usize * width + pt.x as usize;
+
                 match char {
                     '#' => {
-                        walls.push((col_idx as i32, height as i32 - row_idx as i32 - 1).into());
+                        tiles[idx] = Tile::Wall;
+                        walls.push(pt);
+                    }
+                    '*' => {
+                        tiles[idx] = Tile::Dirt;
+                        dirt.push(pt);
                     }
-                    'S' => {
-                        start = (col_idx as i32, height as i32 - row_idx as i32 - 1).into();
+                    'S' | '>' | '<' | '^' | 'v' => {
+                        let dir = match char {
+                            '<' => Dir::West,
+                            '^' => Dir::North,
+                            'v' => Dir::South,
+                            _ => Dir::East,
+                        };
+
+                        if start.replace((pt, dir)).is_some() {
+                            return Err(MapParseError::DuplicateStart { row, col });
+                        }
+                    }
+                    'E' => {
+                        tiles[idx] = Tile::Exit;
+                        if exit.replace(pt).is_some() {
+                            return Err(MapParseError::DuplicateExit { row, col });
+                        }
                     }
                     '.' => {}
-                    c => {
-                        return Err(format!("Unrecognized character in map string: '{c}'"));
+                    char => {
+                        return Err(MapParseError::UnrecognizedChar { char, row, col });
                     }
                 }
             }
         }
 
+        let (start, start_dir) = start.ok_or(MapParseError::MissingStart)?;
+
+        let mut physics = PhysicsSettings::default();
+        // the settings start after the map and the blank line below it
+        for (idx, line) in lines.enumerate() {
+            if !line.trim().is_empty() && !physics.parse_line(line) {
+                return Err(MapParseError::InvalidSetting {
+                    row: height + idx + 2,
+                    line: line.into(),
+                });
+            }
+        }
+
         Ok(Self {
+            tiles,
             walls,
+            dirt,
             start,
+            start_dir,
+            exit,
 
             width,
             height,
+            physics,
         })
     }
 
+    /// Checks that the vac can't reach the edge of the map, ie. that every tile on the
+    /// border is a wall. The vac can't leave the map either way, but a map with gaps in
+    /// its outer wall is almost always a mistake.
+    pub fn check_enclosed(&self) -> Result<(), MapParseError> {
+        let (width, height) = (self.width as i32, self.height as i32);
+
+        let border = (0..height)
+            .flat_map(|y| (0..width).map(move |x| IVec2::new(x, y)))
+            .filter(|pt| pt.x == 0 || pt.y == 0 || pt.x == width - 1 || pt.y == height - 1);
+
+        for pt in border {
+            if self.has_space(pt) {
+                return Err(MapParseError::NotEnclosed {
+                    row: (height - pt.y) as usize,
+                    col: pt.x as usize + 1,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     /// returns (width, height)
     pub fn dimensions(&self) -> (usize, usize) {
         (self.width, self.height)
@@ -63,18 +255,86 @@ impl Map {
         self.start
     }
 
+    /// the direction the vac is facing at the start
+    pub fn start_dir(&self) -> Dir {
+        self.start_dir
+    }
+
+    pub fn exit(&self) -> Option<IVec2> {
+        self.exit
+    }
+
+    pub fn physics(&self) -> PhysicsSettings {
+        self.physics
+    }
+
     pub fn walls(&self) -> &[IVec2] {
         &self.walls
     }
 
-    pub fn has_space(&self, pt: impl Into<IVec2>) -> bool {
+    /// positions of the floor tiles that start out dirty
+    pub fn dirt(&self) -> &[IVec2] {
+        &self.dirt
+    }
+
+    /// returns `None` for points outside of the map
+    pub fn tile(&self, pt: impl Into<IVec2>) -> Option<Tile> {
         let pt: IVec2 = pt.into();
 
-        pt.x >= 0
-            && pt.y >= 0
-            && pt.x < self.width as i32
-            && pt.y < self.height as i32
-            && !self.walls.contains(&pt)
+        let in_bounds =
+            pt.x >= 0 && pt.y >= 0 && pt.x < self.width as i32 && pt.y < self.height as i32;
+        in_bounds.then(|| self.tiles[pt.y as usize * self.width + pt.x as usize])
+    }
+
+    pub fn has_dirt(&self, pt: impl Into<IVec2>) -> bool {
+        self.tile(pt) == Some(Tile::Dirt)
+    }
+
+    pub fn has_space(&self, pt: impl Into<IVec2>) -> bool {
+        self.tile(pt).is_some_and(|tile| tile != Tile::Wall)
+    }
+
+    /// iterates over every position the vac can occupy
+    pub fn floor(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (0..self.height as i32)
+            .flat_map(move |y| (0..self.width as i32).map(move |x| IVec2::new(x, y)))
+            .filter(|pt| self.has_space(*pt))
+    }
+}
+
+/// Writes the map back out in the same format that `Map::parse` accepts
+impl std::fmt::Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in (0..self.height as i32).rev() {
+            for x in 0..self.width as i32 {
+                let pt = IVec2::new(x, y);
+
+                let char = if pt == self.start {
+                    // 'S' is the usual way of writing a start that faces east
+                    match self.start_dir {
+                        Dir::East => 'S',
+                        Dir::North => '^',
+                        Dir::West => '<',
+                        Dir::South => 'v',
+                    }
+                } else {
+                    match self.tile(pt) {
+                        Some(Tile::Wall) => '#',
+                        Some(Tile::Dirt) => '*',
+                        Some(Tile::Exit) => 'E',
+                        Some(Tile::Floor) | None => '.',
+                    }
+                };
+                write!(f, "{char}")?;
+            }
+            writeln!(f)?;
+        }
+
+        if self.physics != PhysicsSettings::default() {
+            write!(f, "\n{}", self.physics)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -108,13 +368,58 @@ impl Map {
     pub const ROOM_4X4: &str = r"####
 #..#
 #S.#
+####";
+
+    /// #####
+    /// #S.E#
+    /// #####
+    pub const CORRIDOR_5X3: &str = r"#####
+#S.E#
+#####";
+
+    /// ####
+    /// #**#
+    /// #S*#
+    /// ####
+    pub const DIRTY_ROOM_4X4: &str = r"####
+#**#
+#S*#
 ####";
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
     use super::*;
 
+    /// builds the text for a random map with a single start and at most one exit
+    fn random_map_str(rng: &mut impl Rng) -> String {
+        let width = rng.random_range(1..20);
+        let height = rng.random_range(1..20);
+
+        let mut rows = (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| ['#', '.', '*'][rng.random_range(0..3)])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        rows[rng.random_range(0..height)][rng.random_range(0..width)] =
+            ['S', '^', '<', 'v'][rng.random_range(0..4)];
+        if rng.random_bool(0.5) {
+            let (row, col) = (rng.random_range(0..height), rng.random_range(0..width));
+            if rows[row][col] == '.' {
+                rows[row][col] = 'E';
+            }
+        }
+
+        rows.into_iter()
+            .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+            .collect()
+    }
+
     #[test]
     fn parse_map_from_str() {
         let map = Map::parse(Map::BIG_LOOP_5X6).unwrap();
@@ -123,6 +428,170 @@ mod tests {
         assert_eq!(map.start(), (2, 1).into());
     }
 
+    #[test]
+    fn parse_dirt_from_str() {
+        let map = Map::parse(Map::DIRTY_ROOM_4X4).unwrap();
+
+        assert_eq!(map.dirt().len(), 3);
+        assert!(map.has_dirt((2, 1)));
+        assert!(map.has_dirt((1, 2)));
+        assert!(!map.has_dirt((1, 1)));
+
+        // dirty tiles are still floor
+        assert!(map.has_space((2, 1)));
+    }
+
+    #[test]
+    fn parse_start_dir_from_str() {
+        let map = Map::parse(Map::ROOM_4X4).unwrap();
+        assert_eq!(map.start_dir(), Dir::East);
+
+        for (char, dir) in [
+            ('>', Dir::East),
+            ('^', Dir::North),
+            ('<', Dir::West),
+            ('v', Dir::South),
+        ] {
+            let map = Map::parse(&format!("###\n#{char}#\n###")).unwrap();
+            assert_eq!(map.start(), (1, 1).into());
+            assert_eq!(map.start_dir(), dir);
+        }
+
+        assert_eq!(
+            Map::parse("#####\n#S.^#\n#####"),
+            Err(MapParseError::DuplicateStart { row: 2, col: 4 })
+        );
+    }
+
+    #[test]
+    fn parse_exit_from_str() {
+        let map = Map::parse(Map::CORRIDOR_5X3).unwrap();
+        assert_eq!(map.exit(), Some((3, 1).into()));
+
+        let map = Map::parse(Map::ROOM_4X4).unwrap();
+        assert_eq!(map.exit(), None);
+    }
+
+    #[test]
+    fn test_floor() {
+        let map = Map::parse(Map::ROOM_4X4).unwrap();
+        let floor = map.floor().collect::<Vec<_>>();
+
+        assert_eq!(floor.len(), 4);
+        assert!(floor.contains(&(1, 1).into()));
+        assert!(floor.contains(&(2, 2).into()));
+    }
+
+    #[test]
+    fn map_to_string() {
+        let map = Map::parse(Map::BIG_LOOP_5X6).unwrap();
+        assert_eq!(map.to_string(), Map::BIG_LOOP_5X6);
+
+        let map = Map::parse(Map::CORRIDOR_5X3).unwrap();
+        assert_eq!(map.to_string(), format!("{}\n", Map::CORRIDOR_5X3));
+
+        let str = format!(
+            "{}\nturn cooldown: 2\nbump costs tick: yes\n",
+            Map::BIG_LOOP_5X6
+        );
+        let map = Map::parse(&str).unwrap();
+        assert_eq!(map.to_string(), str);
+    }
+
+    #[test]
+    fn parse_physics() {
+        let map = Map::parse("###\n#S#\n###\n\nturn and move: yes\n\n").unwrap();
+        assert_eq!(map.physics().turn_and_move, Some(true));
+        assert_eq!(map.physics().turn_cooldown, None);
+        assert_eq!(map.dimensions(), (3, 3));
+
+        assert_eq!(
+            Map::parse("###\n#S#\n###\n\nturn cooldown: 1\ngravity: 2"),
+            Err(MapParseError::InvalidSetting {
+                row: 6,
+                line: "gravity: 2".into()
+            })
+        );
+    }
+
+    #[test]
+    fn map_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..500 {
+            let str = random_map_str(&mut rng);
+            let map = Map::parse(&str).unwrap();
+
+            assert_eq!(map.to_string(), str);
+            assert_eq!(Map::parse(&map.to_string()).unwrap(), map);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Map::parse(""), Err(MapParseError::Empty));
+        assert_eq!(Map::parse("...\n..."), Err(MapParseError::MissingStart));
+
+        assert_eq!(
+            Map::parse("###\n#S?\n###"),
+            Err(MapParseError::UnrecognizedChar {
+                char: '?',
+                row: 2,
+                col: 3
+            })
+        );
+        assert_eq!(
+            Map::parse("###\n#S.#\n###"),
+            Err(MapParseError::RaggedRow {
+                row: 2,
+                width: 4,
+                expected: 3
+            })
+        );
+        assert_eq!(
+            Map::parse("#####\n#S.S#\n#####"),
+            Err(MapParseError::DuplicateStart { row: 2, col: 4 })
+        );
+        assert_eq!(
+            Map::parse("#####\n#SEE#\n#####"),
+            Err(MapParseError::DuplicateExit { row: 2, col: 4 })
+        );
+    }
+
+    #[test]
+    fn check_enclosed() {
+        assert!(
+            Map::parse(Map::BIG_LOOP_5X6)
+                .unwrap()
+                .check_enclosed()
+                .is_ok()
+        );
+        assert!(Map::parse(Map::ROOM_4X4).unwrap().check_enclosed().is_ok());
+
+        assert_eq!(
+            Map::parse(Map::EMPTY_3X3).unwrap().check_enclosed(),
+            Err(MapParseError::NotEnclosed { row: 3, col: 1 })
+        );
+        assert_eq!(
+            Map::parse("####\n#S..\n####").unwrap().check_enclosed(),
+            Err(MapParseError::NotEnclosed { row: 2, col: 4 })
+        );
+    }
+
+    #[test]
+    fn test_tile() {
+        let map = Map::parse(Map::CORRIDOR_5X3).unwrap();
+
+        assert_eq!(map.tile((0, 0)), Some(Tile::Wall));
+        assert_eq!(map.tile((1, 1)), Some(Tile::Floor));
+        assert_eq!(map.tile((3, 1)), Some(Tile::Exit));
+        assert_eq!(map.tile((5, 1)), None);
+        assert_eq!(map.tile((1, -1)), None);
+
+        let map = Map::parse(Map::DIRTY_ROOM_4X4).unwrap();
+        assert_eq!(map.tile((2, 2)), Some(Tile::Dirt));
+    }
+
     #[test]
     fn test_has_space() {
         let map = Map::parse(Map::BIG_LOOP_5X6).unwrap();
@@ -1,7 +1,17 @@
 pub mod command;
+pub mod condition;
+pub mod dsl;
+pub mod history;
+pub mod level;
 pub mod map;
+pub mod physics;
+pub mod program;
+pub mod register;
 pub mod rule;
+pub mod runner;
+pub mod save;
 pub mod sensor;
+pub mod share;
 pub mod state;
 
 // TODO: put this in a different module?
@@ -0,0 +1,171 @@
+/// The constraints the vac moves under, which levels can tighten or relax
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Physics {
+    /// number of ticks after a turn during which the vac can't turn again
+    pub turn_cooldown: u32,
+    /// whether bumping into a wall leaves the vac stunned for the next tick
+    pub bump_costs_tick: bool,
+    /// whether the vac still moves on a tick where it turns, instead of only turning
+    pub turn_and_move: bool,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            turn_cooldown: 1,
+            bump_costs_tick: false,
+            turn_and_move: false,
+        }
+    }
+}
+
+impl Physics {
+    /// how the command-line flags that `apply_arg` understands are used
+    pub const USAGE: &str = "[--turn-cooldown <ticks>] [--bump-costs-tick] [--turn-and-move]";
+
+    pub fn with_turn_cooldown(mut self, turn_cooldown: u32) -> Self {
+        self.turn_cooldown = turn_cooldown;
+        self
+    }
+
+    pub fn with_bump_costs_tick(mut self, bump_costs_tick: bool) -> Self {
+        self.bump_costs_tick = bump_costs_tick;
+        self
+    }
+
+    pub fn with_turn_and_move(mut self, turn_and_move: bool) -> Self {
+        self.turn_and_move = turn_and_move;
+        self
+    }
+
+    /// Applies a command-line flag such as `--turn-cooldown 2`, taking its value from `args`.
+    /// Returns whether the flag was one about physics at all.
+    pub fn apply_arg(
+        &mut self,
+        arg: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<bool, String> {
+        match arg {
+            "--turn-cooldown" => {
+                let value = args.next().ok_or("Missing value for --turn-cooldown")?;
+                self.turn_cooldown = value
+                    .parse()
+                    .map_err(|_| format!("Invalid turn cooldown: '{value}'"))?;
+            }
+            "--bump-costs-tick" => self.bump_costs_tick = true,
+            "--turn-and-move" => self.turn_and_move = true,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+}
+
+/// The parts of `Physics` that a level sets for itself, written at the bottom of its map
+/// file, eg. "turn cooldown: 2". Anything a level leaves out is up to the game's defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhysicsSettings {
+    pub turn_cooldown: Option<u32>,
+    pub bump_costs_tick: Option<bool>,
+    pub turn_and_move: Option<bool>,
+}
+
+impl PhysicsSettings {
+    /// reads a setting written the way `Display` writes it, returning whether it was valid
+    pub fn parse_line(&mut self, line: &str) -> bool {
+        let Some((name, value)) = line.split_once(':') else {
+            return false;
+        };
+        let value = value.trim();
+        let flag = || match value {
+            "yes" => Some(true),
+            "no" => Some(false),
+            _ => None,
+        };
+
+        match name.trim() {
+            "turn cooldown" => value
+                .parse()
+                .ok()
+                .map(|ticks| self.turn_cooldown = Some(ticks)),
+            "bump costs tick" => flag().map(|set| self.bump_costs_tick = Some(set)),
+            "turn and move" => flag().map(|set| self.turn_and_move = Some(set)),
+            _ => None,
+        }
+        .is_some()
+    }
+
+    /// `physics` with the settings that the level sets replaced
+    pub fn apply(&self, physics: Physics) -> Physics {
+        Physics {
+            turn_cooldown: self.turn_cooldown.unwrap_or(physics.turn_cooldown),
+            bump_costs_tick: self.bump_costs_tick.unwrap_or(physics.bump_costs_tick),
+            turn_and_move: self.turn_and_move.unwrap_or(physics.turn_and_move),
+        }
+    }
+}
+
+/// Writes each setting that's set on a line of its own
+impl std::fmt::Display for PhysicsSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flag = |set: bool| if set { "yes" } else { "no" };
+
+        if let Some(turn_cooldown) = self.turn_cooldown {
+            writeln!(f, "turn cooldown: {turn_cooldown}")?;
+        }
+        if let Some(bump_costs_tick) = self.bump_costs_tick {
+            writeln!(f, "bump costs tick: {}", flag(bump_costs_tick))?;
+        }
+        if let Some(turn_and_move) = self.turn_and_move {
+            writeln!(f, "turn and move: {}", flag(turn_and_move))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_args() {
+        let mut physics = Physics::default();
+        let mut args = ["3", "--bump-costs-tick"].map(String::from).into_iter();
+
+        assert_eq!(physics.apply_arg("--turn-cooldown", &mut args), Ok(true));
+        assert_eq!(physics.apply_arg("--turn-and-move", &mut args), Ok(true));
+        assert_eq!(physics.apply_arg("--ticks", &mut args), Ok(false));
+        assert_eq!(
+            physics,
+            Physics::default()
+                .with_turn_cooldown(3)
+                .with_turn_and_move(true)
+        );
+
+        // neither another flag nor nothing at all is a valid cooldown
+        assert!(physics.apply_arg("--turn-cooldown", &mut args).is_err());
+        assert!(physics.apply_arg("--turn-cooldown", &mut args).is_err());
+    }
+
+    #[test]
+    fn settings() {
+        let mut settings = PhysicsSettings::default();
+        assert!(settings.parse_line("turn cooldown: 0"));
+        assert!(settings.parse_line("turn and move:yes"));
+        assert!(!settings.parse_line("turn and move: maybe"));
+        assert!(!settings.parse_line("gravity: 2"));
+        assert!(!settings.parse_line("turn cooldown"));
+        assert_eq!(
+            settings.to_string(),
+            "turn cooldown: 0\nturn and move: yes\n"
+        );
+
+        // whatever the level leaves out comes from the physics it's applied to
+        let physics = Physics::default().with_bump_costs_tick(true);
+        assert_eq!(
+            settings.apply(physics),
+            physics.with_turn_cooldown(0).with_turn_and_move(true)
+        );
+    }
+}
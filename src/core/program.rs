@@ -0,0 +1,313 @@
+use bevy_egui::egui::WidgetText;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    command::Command,
+    dsl::{self, is_valid_mode_name},
+    rule::Rule,
+};
+
+/// What the vac does on a tick where no rule told it to move or turn
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DefaultAction {
+    #[default]
+    Forward,
+    Wait,
+    /// back up one tile, keeping the same heading
+    Reverse,
+}
+
+impl DefaultAction {
+    pub const ALL: [DefaultAction; 3] = [
+        DefaultAction::Forward,
+        DefaultAction::Wait,
+        DefaultAction::Reverse,
+    ];
+}
+
+impl From<DefaultAction> for String {
+    fn from(val: DefaultAction) -> Self {
+        match val {
+            DefaultAction::Forward => "OTHERWISE go forward",
+            DefaultAction::Wait => "OTHERWISE wait",
+            DefaultAction::Reverse => "OTHERWISE reverse",
+        }
+        .into()
+    }
+}
+
+impl std::fmt::Display for DefaultAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from(*self))
+    }
+}
+
+impl From<DefaultAction> for WidgetText {
+    fn from(val: DefaultAction) -> Self {
+        String::from(val).into()
+    }
+}
+
+/// A named list of rules. Only the rules of the mode the vac is in are checked each tick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mode {
+    name: String,
+    rules: Vec<Rule>,
+}
+
+impl Mode {
+    pub fn new(name: impl Into<String>, rules: impl Into<Vec<Rule>>) -> Self {
+        Self {
+            name: name.into(),
+            rules: rules.into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn rules_mut(&mut self) -> &mut Vec<Rule> {
+        &mut self.rules
+    }
+}
+
+/// The rules the player has written, organised into modes, along with what to do when none
+/// of them steer the vac. The vac starts out in the first mode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    /// never empty
+    modes: Vec<Mode>,
+    #[serde(default)]
+    default_action: DefaultAction,
+}
+
+/// Checks that a mode can be given `name` when `modes` come before it, the same way
+/// whether the program is read from text or loaded from a save
+pub(crate) fn check_mode_name(modes: &[Mode], name: &str) -> Result<(), String> {
+    check_mode_target(name)?;
+    if modes.iter().any(|mode| mode.name == name) {
+        return Err(format!("Mode '{name}' already exists"));
+    }
+    Ok(())
+}
+
+/// Checks that a rule could switch to a mode called `name`, whether or not it exists
+pub(crate) fn check_mode_target(name: &str) -> Result<(), String> {
+    if !is_valid_mode_name(name) {
+        return Err(format!("Invalid mode name '{name}'"));
+    }
+    Ok(())
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Self::new([])
+    }
+}
+
+impl Program {
+    /// name of the mode a program starts out with
+    pub const MAIN_MODE: &str = "main";
+
+    /// a program with a single mode
+    pub fn new(rules: impl Into<Vec<Rule>>) -> Self {
+        Self::with_modes([Mode::new(Self::MAIN_MODE, rules)])
+    }
+
+    /// a program with several modes, starting out in the first one
+    pub fn with_modes(modes: impl Into<Vec<Mode>>) -> Self {
+        let modes = modes.into();
+        assert!(!modes.is_empty(), "a program needs at least one mode");
+
+        Self {
+            modes,
+            default_action: DefaultAction::default(),
+        }
+    }
+
+    pub fn with_default_action(mut self, default_action: DefaultAction) -> Self {
+        self.default_action = default_action;
+        self
+    }
+
+    pub fn modes(&self) -> &[Mode] {
+        &self.modes
+    }
+
+    pub fn mode(&self, idx: usize) -> Option<&Mode> {
+        self.modes.get(idx)
+    }
+
+    pub fn mode_mut(&mut self, idx: usize) -> Option<&mut Mode> {
+        self.modes.get_mut(idx)
+    }
+
+    pub fn mode_idx(&self, name: &str) -> Option<usize> {
+        self.modes.iter().position(|mode| mode.name == name)
+    }
+
+    /// adds an empty mode with a name that isn't taken yet, and returns its index
+    pub fn add_mode(&mut self) -> usize {
+        let name = (self.modes.len() + 1..)
+            .map(|n| format!("mode{n}"))
+            .find(|name| self.mode_idx(name).is_none())
+            .unwrap();
+
+        self.modes.push(Mode::new(name, []));
+        self.modes.len() - 1
+    }
+
+    /// Removes a mode, as long as it isn't the only one. Rules that switch to it are left in
+    /// place, and do nothing until a mode with that name exists again.
+    pub fn remove_mode(&mut self, idx: usize) {
+        if self.modes.len() > 1 {
+            self.modes.remove(idx);
+        }
+    }
+
+    /// renames a mode along with every rule that switches to it, as long as the new name
+    /// is valid and not taken by another mode
+    pub fn rename_mode(&mut self, idx: usize, name: &str) -> bool {
+        if !is_valid_mode_name(name) || self.mode_idx(name).is_some_and(|other| other != idx) {
+            return false;
+        }
+
+        let old = std::mem::replace(&mut self.modes[idx].name, name.into());
+        for rule in self.modes.iter_mut().flat_map(|mode| &mut mode.rules) {
+            if let Command::GoToMode(target) = rule.command_mut()
+                && *target == old
+            {
+                *target = name.into();
+            }
+        }
+
+        true
+    }
+
+    /// number of rules across all modes
+    pub fn rule_count(&self) -> usize {
+        self.modes.iter().map(|mode| mode.rules.len()).sum()
+    }
+
+    pub fn default_action(&self) -> DefaultAction {
+        self.default_action
+    }
+
+    pub fn set_default_action(&mut self, default_action: DefaultAction) {
+        self.default_action = default_action;
+    }
+}
+
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", dsl::print(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{condition::Condition, sensor::Sensor};
+
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let program = dsl::parse(
+            r"// follow the right wall
+WHEN space right THEN turn right
+
+WHEN hit wall   THEN turn left",
+        )
+        .unwrap();
+
+        assert_eq!(program.modes().len(), 1);
+        let rules = program.modes()[0].rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].condition(), &Condition::Sensor(Sensor::SpaceRight));
+        assert_eq!(rules[0].command(), &Command::TurnRight);
+        assert_eq!(rules[1].condition(), &Condition::Sensor(Sensor::HitWall));
+        assert_eq!(rules[1].command(), &Command::TurnLeft);
+        assert_eq!(program.default_action(), DefaultAction::Forward);
+    }
+
+    #[test]
+    fn parse_default_action() {
+        let program = dsl::parse("WHEN hit wall THEN turn left\nOTHERWISE reverse").unwrap();
+        assert_eq!(program.rule_count(), 1);
+        assert_eq!(program.default_action(), DefaultAction::Reverse);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = dsl::parse("WHEN hit wall THEN turn left\nWHEN hit wall THEN fly")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 2"));
+
+        let err = dsl::parse("OTHERWISE wait\n\nOTHERWISE reverse")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 3"));
+        assert!(err.contains("line 1"));
+
+        // moving forward isn't something rules can ask for
+        assert!(dsl::parse("WHEN hit wall OTHERWISE go forward").is_err());
+    }
+
+    #[test]
+    fn parse_modes() {
+        let program = dsl::parse(
+            r"WHEN hit wall THEN go to mode search
+MODE search
+WHEN space left THEN turn left
+WHEN hit wall THEN go to mode main",
+        )
+        .unwrap();
+
+        let names = program.modes().iter().map(Mode::name).collect::<Vec<_>>();
+        assert_eq!(names, ["main", "search"]);
+        assert_eq!(program.modes()[1].rules().len(), 2);
+        assert_eq!(program.rule_count(), 3);
+
+        // a program can start with a mode of its own
+        let program = dsl::parse("MODE a\nMODE b").unwrap();
+        assert_eq!(program.mode_idx("a"), Some(0));
+        assert_eq!(program.mode_idx("main"), None);
+
+        assert!(dsl::parse("MODE a\nMODE a").is_err());
+        assert!(dsl::parse("MODE two words").is_err());
+
+        // switching to a mode that doesn't exist does nothing, the same as in a running game
+        let program = dsl::parse("WHEN hit wall THEN go to mode nowhere").unwrap();
+        assert_eq!(program.modes().len(), 1);
+    }
+
+    #[test]
+    fn edit_modes() {
+        let mut program =
+            Program::new([Rule::new(Sensor::HitWall, Command::GoToMode("main".into()))]);
+
+        let idx = program.add_mode();
+        assert_eq!(program.modes()[idx].name(), "mode2");
+
+        // renaming a mode updates the rules that switch to it
+        assert!(program.rename_mode(0, "start"));
+        assert_eq!(
+            program.modes()[0].rules()[0].command(),
+            &Command::GoToMode("start".into())
+        );
+        assert!(!program.rename_mode(0, "mode2"));
+        assert!(!program.rename_mode(0, ""));
+        assert!(!program.rename_mode(0, "a//b"));
+        assert!(!program.rename_mode(0, "THEN"));
+
+        program.remove_mode(0);
+        program.remove_mode(0);
+        assert_eq!(program.modes().len(), 1);
+    }
+}
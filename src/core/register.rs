@@ -0,0 +1,49 @@
+//! Named flags and counters that rules can set and test, giving programs a little memory
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Flag {
+    A,
+    B,
+    C,
+    D,
+}
+
+impl Flag {
+    pub const ALL: [Flag; 4] = [Flag::A, Flag::B, Flag::C, Flag::D];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Flag::A => "a",
+            Flag::B => "b",
+            Flag::C => "c",
+            Flag::D => "d",
+        }
+    }
+
+    pub(crate) fn idx(&self) -> usize {
+        *self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Counter {
+    X,
+    Y,
+}
+
+impl Counter {
+    pub const ALL: [Counter; 2] = [Counter::X, Counter::Y];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::X => "x",
+            Counter::Y => "y",
+        }
+    }
+
+    pub(crate) fn idx(&self) -> usize {
+        *self as usize
+    }
+}
@@ -1,65 +1,109 @@
-use crate::core::{command::Command, sensor::Sensor};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+use crate::core::{
+    command::Command,
+    condition::Condition,
+    sensor::{Readings, Sensor},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Rule {
-    sensor: Sensor,
+    condition: Condition,
     command: Command,
+    /// disabled rules stay in the program but never fire
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
 }
 
 impl Rule {
+    /// a rule that fires whenever a single sensor is active
     pub const fn new(sensor: Sensor, command: Command) -> Self {
-        Self { sensor, command }
+        Self {
+            condition: Condition::Sensor(sensor),
+            command,
+            enabled: true,
+        }
+    }
+
+    pub fn with_condition(condition: Condition, command: Command) -> Self {
+        Self {
+            condition,
+            command,
+            enabled: true,
+        }
+    }
+
+    pub fn condition(&self) -> &Condition {
+        &self.condition
+    }
+
+    pub fn condition_mut(&mut self) -> &mut Condition {
+        &mut self.condition
+    }
+
+    pub fn command(&self) -> &Command {
+        &self.command
     }
 
-    pub fn sensor(&self) -> Sensor {
-        self.sensor
+    pub fn command_mut(&mut self) -> &mut Command {
+        &mut self.command
     }
 
-    pub fn command(&self) -> Command {
-        self.command
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
     }
 
-    pub fn compute_commands(rules: &[Rule], sensor: &[Sensor]) -> Vec<Command> {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn compute_commands(rules: &[Rule], readings: &Readings) -> Vec<Command> {
         let mut commands = Vec::new();
 
         for rule in rules {
-            for sensor in sensor {
-                // check for rule match
-                if rule.sensor != *sensor {
-                    continue;
-                }
-
-                // filter out commands that already have a member of their category in
-                // the output commands
-                // NOTE: this is trivial at the moment as there's only one category
-                let category_already_represented = !commands.is_empty();
-                if !category_already_represented {
-                    commands.push(rule.command);
-                }
+            // check for rule match
+            if !rule.enabled || !rule.condition.matches(readings) {
+                continue;
             }
-        }
 
-        // if no movement command was fired, fall back to moving forward
-        // NOTE: update this logic when we add categories
-        if commands.is_empty() {
-            commands.push(Command::MoveForward);
+            // filter out commands that already have a member of their category in
+            // the output commands
+            let category_already_represented = commands
+                .iter()
+                .any(|command: &Command| command.category() == rule.command.category());
+            if !category_already_represented {
+                commands.push(rule.command.clone());
+            }
         }
 
         commands
     }
 }
 
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WHEN {} {}", self.condition, self.command)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::core::dsl::parse_rule;
+
     use super::*;
 
     #[test]
-    fn compute_commands_default_move() {
+    fn compute_commands_none() {
         let rules = [Rule::new(Sensor::HitWall, Command::TurnRight)];
         let sensor = [Sensor::SpaceLeft];
 
-        let commands = Rule::compute_commands(&rules, &sensor);
-        assert_eq!(commands, vec![Command::MoveForward]);
+        // what to do when no rule fires is up to the program's default action
+        let commands = Rule::compute_commands(&rules, &Readings::new(sensor));
+        assert!(commands.is_empty());
     }
 
     #[test]
@@ -67,7 +111,7 @@ mod tests {
         let rules = [Rule::new(Sensor::HitWall, Command::TurnRight)];
         let sensors = [Sensor::HitWall];
 
-        let commands = Rule::compute_commands(&rules, &sensors);
+        let commands = Rule::compute_commands(&rules, &Readings::new(sensors));
         assert_eq!(commands, vec![Command::TurnRight]);
     }
 
@@ -80,7 +124,76 @@ mod tests {
         let sensors = [Sensor::HitWall];
 
         // only the first matching rule should trigger
-        let commands = Rule::compute_commands(&rules, &sensors);
+        let commands = Rule::compute_commands(&rules, &Readings::new(sensors));
         assert_eq!(commands, vec![Command::TurnRight]);
     }
+
+    #[test]
+    fn compute_commands_multiple_categories() {
+        let rules = [
+            Rule::new(Sensor::SpaceLeft, Command::Beep),
+            Rule::new(Sensor::HitWall, Command::TurnLeft),
+            Rule::new(Sensor::SpaceLeft, Command::Vacuum),
+            Rule::new(Sensor::HitWall, Command::Brush),
+        ];
+        let sensors = [Sensor::HitWall, Sensor::SpaceLeft];
+
+        // one command from each category
+        let commands = Rule::compute_commands(&rules, &Readings::new(&sensors[1..]));
+        assert_eq!(commands, vec![Command::Beep, Command::Vacuum]);
+
+        let commands = Rule::compute_commands(&rules, &Readings::new(sensors));
+        assert_eq!(
+            commands,
+            vec![Command::Beep, Command::TurnLeft, Command::Vacuum]
+        );
+    }
+
+    #[test]
+    fn compute_commands_compound() {
+        let rules = [Rule::with_condition(
+            Condition::And(vec![
+                Sensor::SpaceLeft.into(),
+                Condition::Not(Box::new(Sensor::HitWall.into())),
+            ]),
+            Command::TurnLeft,
+        )];
+
+        let commands = Rule::compute_commands(&rules, &Readings::new([Sensor::SpaceLeft]));
+        assert_eq!(commands, vec![Command::TurnLeft]);
+
+        let commands =
+            Rule::compute_commands(&rules, &Readings::new([Sensor::SpaceLeft, Sensor::HitWall]));
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn compute_commands_disabled() {
+        let mut rules = [
+            Rule::new(Sensor::HitWall, Command::TurnRight),
+            Rule::new(Sensor::HitWall, Command::TurnLeft),
+        ];
+        rules[0].set_enabled(false);
+
+        // the disabled rule doesn't block the next one in its category either
+        let commands = Rule::compute_commands(&rules, &Readings::new([Sensor::HitWall]));
+        assert_eq!(commands, vec![Command::TurnLeft]);
+    }
+
+    #[test]
+    fn parse() {
+        let rule = parse_rule("WHEN space left AND NOT hit wall THEN turn left").unwrap();
+        assert_eq!(rule.command(), &Command::TurnLeft);
+        assert_eq!(
+            rule.to_string(),
+            "WHEN space left AND NOT hit wall THEN turn left"
+        );
+
+        let rule = parse_rule("WHEN hit wall THEN go to mode search").unwrap();
+        assert_eq!(rule.command(), &Command::GoToMode("search".into()));
+
+        assert!(parse_rule("WHEN space left THEN").is_err());
+        assert!(parse_rule("WHEN space left THEN go to mode two words").is_err());
+        assert!(parse_rule("space left THEN turn left").is_err());
+    }
 }
@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use crate::core::{
+    map::Map,
+    physics::Physics,
+    program::Program,
+    state::{Effect, State},
+};
+
+/// Everything that happened while running a program against a map
+#[derive(Debug, Clone)]
+pub struct RunReport {
+    pub final_state: State,
+
+    /// the effects of every tick, in order
+    pub effects: Vec<Vec<Effect>>,
+
+    pub bumps: usize,
+
+    /// whether the run was cut short because the vac got back into a state it had
+    /// already been in, meaning it would repeat the same ticks forever
+    pub looped: bool,
+}
+
+impl RunReport {
+    pub fn ticks(&self) -> usize {
+        self.effects.len()
+    }
+
+    /// fraction of the map's floor tiles that the vac has visited
+    pub fn coverage(&self, map: &Map) -> f32 {
+        let floor = map.floor().count();
+        if floor == 0 {
+            return 1.;
+        }
+
+        self.final_state.visit_counts().len() as f32 / floor as f32
+    }
+}
+
+/// Runs the program against the map from its start position until either the tick limit is
+/// reached or the vac gets stuck in a loop
+pub fn run(map: &Map, program: &Program, physics: Physics, tick_limit: usize) -> RunReport {
+    let mut state = State::at_start(map);
+
+    let mut effects = Vec::new();
+    let mut bumps = 0;
+    let mut looped = false;
+
+    let mut seen = HashSet::from([state.snapshot()]);
+
+    while effects.len() < tick_limit {
+        let tick_effects = state.tick(map, program, physics);
+
+        bumps += tick_effects
+            .iter()
+            .filter(|effect| effect.is_bump())
+            .count();
+        effects.push(tick_effects);
+
+        if !seen.insert(state.snapshot()) {
+            looped = true;
+            break;
+        }
+    }
+
+    RunReport {
+        final_state: state,
+        effects,
+        bumps,
+        looped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{command::Command, dir::Dir, rule::Rule, sensor::Sensor};
+
+    use super::*;
+
+    #[test]
+    fn run_until_tick_limit() {
+        let map = Map::parse(Map::BIG_LOOP_5X6).unwrap();
+        let program = Program::new([Rule::new(Sensor::HitWall, Command::TurnLeft)]);
+
+        let report = run(&map, &program, Physics::default(), 5);
+        assert_eq!(report.ticks(), 5);
+        assert!(!report.looped);
+        assert_eq!(report.final_state.vac_pos(), (3, 3).into());
+        assert_eq!(report.final_state.vac_dir(), Dir::North);
+        assert_eq!(report.bumps, 1);
+        assert_eq!(report.final_state.visit_counts().len(), 4);
+    }
+
+    #[test]
+    fn run_detects_loop() {
+        let map = Map::parse(Map::BIG_LOOP_5X6).unwrap();
+        let program = Program::new([Rule::new(Sensor::HitWall, Command::TurnLeft)]);
+
+        // following the wall around the loop brings the vac back to where it started
+        let report = run(&map, &program, Physics::default(), 1000);
+        assert!(report.looped);
+        assert!(report.ticks() < 1000);
+        assert_eq!(report.coverage(&map), 1.);
+    }
+
+    #[test]
+    fn run_cleans_dirt() {
+        let map = Map::parse(Map::DIRTY_ROOM_4X4).unwrap();
+        let program = Program::new([Rule::new(Sensor::HitWall, Command::TurnLeft)]);
+
+        let report = run(&map, &program, Physics::default(), 100);
+        assert!(report.looped);
+        assert_eq!(report.final_state.remaining_dirt(&map), 0);
+        assert!(
+            report
+                .effects
+                .iter()
+                .flatten()
+                .any(|effect| matches!(effect, Effect::Cleaned { .. }))
+        );
+    }
+}
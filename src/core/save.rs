@@ -0,0 +1,211 @@
+//! Programs saved to disk as JSON, tagged with the version of the format they were written
+//! in so that saves from older versions of the game keep loading
+
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::core::{
+    command::Command,
+    dsl,
+    program::{Program, check_mode_name, check_mode_target},
+};
+
+/// version of the format that `save` writes
+pub const VERSION: u32 = 1;
+
+/// Upgrades a save from one version of the format to the next, with the upgrade from
+/// version `n` at index `n - 1`. Adding sensors or commands doesn't call for one, since older
+/// saves simply don't use them, and neither do new fields that have a serde default. Renaming
+/// or removing anything does.
+const MIGRATIONS: [fn(&mut Value); VERSION as usize - 1] = [];
+
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u32,
+    program: &'a Program,
+}
+
+pub fn save(program: &Program) -> String {
+    let file = SaveFile {
+        version: VERSION,
+        program,
+    };
+    serde_json::to_string_pretty(&file).expect("programs only contain JSON-friendly types")
+}
+
+/// loads a program saved by this or an older version of the game
+pub fn load(str: &str) -> Result<Program, String> {
+    let mut value: Value =
+        serde_json::from_str(str).map_err(|err| format!("Invalid save file: {err}"))?;
+
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or("Save file has no version")?;
+    if version == 0 || version > u64::from(VERSION) {
+        return Err(format!(
+            "Save file version {version} isn't supported, only versions up to {VERSION} are"
+        ));
+    }
+
+    for migrate in &MIGRATIONS[version as usize - 1..] {
+        migrate(&mut value);
+    }
+
+    let program = value
+        .get_mut("program")
+        .map(Value::take)
+        .ok_or("Save file has no program")?;
+    let program: Program = serde_json::from_value(program)
+        .map_err(|err| format!("Invalid program in save file: {err}"))?;
+
+    if program.modes().is_empty() {
+        return Err("Program in save file has no modes".into());
+    }
+    check_modes(&program).map_err(|err| format!("Invalid program in save file: {err}"))?;
+
+    Ok(program)
+}
+
+/// checks the mode names and the modes that rules switch to, the same as `dsl::parse` does
+fn check_modes(program: &Program) -> Result<(), String> {
+    for (idx, mode) in program.modes().iter().enumerate() {
+        check_mode_name(&program.modes()[..idx], mode.name())?;
+
+        for rule in mode.rules() {
+            if let Command::GoToMode(target) = rule.command() {
+                check_mode_target(target)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// whether the file at the path is a save, rather than a program in the text format
+fn is_save_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+/// Reads a program from a file, which is a save if it ends in `.json` and written in the
+/// text format otherwise
+pub fn read_file(path: impl AsRef<Path>) -> Result<Program, String> {
+    let path = path.as_ref();
+    let str = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+
+    if is_save_file(path) {
+        load(&str)
+    } else {
+        dsl::parse(&str).map_err(|err| err.to_string())
+    }
+}
+
+/// writes a program to a file, in the format `read_file` expects given its extension
+pub fn write_file(path: impl AsRef<Path>, program: &Program) -> Result<(), String> {
+    let path = path.as_ref();
+    let str = if is_save_file(path) {
+        save(program)
+    } else {
+        dsl::print(program)
+    };
+
+    std::fs::write(path, str).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{program::DefaultAction, register::Counter};
+
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let program = dsl::parse(
+            r"WHEN hit wall AND NOT flag a set THEN go to mode search
+WHEN counter x above 3 THEN set flag b
+OTHERWISE reverse
+MODE search
+WHEN (space left OR space right) AND NOT always THEN increment counter y",
+        )
+        .unwrap();
+
+        assert_eq!(load(&save(&program)), Ok(program));
+    }
+
+    #[test]
+    fn load_defaults() {
+        // saves don't have to spell out everything that has a sensible default
+        let program = load(
+            r#"{
+                "version": 1,
+                "program": {
+                    "modes": [{
+                        "name": "main",
+                        "rules": [{
+                            "condition": { "Sensor": { "FlagSet": "A" } },
+                            "command": { "Increment": "X" }
+                        }]
+                    }]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let rule = &program.modes()[0].rules()[0];
+        assert!(rule.is_enabled());
+        assert_eq!(rule.condition().to_string(), "flag a set");
+        assert_eq!(rule.command(), &Command::Increment(Counter::X));
+        assert_eq!(program.default_action(), DefaultAction::Forward);
+    }
+
+    #[test]
+    fn load_errors() {
+        assert!(load("not json").is_err());
+        assert!(load(r#"{ "program": { "modes": [] } }"#).is_err());
+        let newer = format!(
+            r#"{{ "version": {}, "program": {{ "modes": [] }} }}"#,
+            VERSION + 1
+        );
+        assert!(load(&newer).unwrap_err().contains("isn't supported"));
+        assert!(load(r#"{ "version": 1 }"#).is_err());
+        assert!(load(r#"{ "version": 1, "program": { "modes": [] } }"#).is_err());
+
+        let err = load(
+            r#"{
+                "version": 1,
+                "program": {
+                    "modes": [{
+                        "name": "main",
+                        "rules": [{ "condition": { "Sensor": "Teleported" }, "command": "Beep" }]
+                    }]
+                }
+            }"#,
+        )
+        .unwrap_err();
+        assert!(err.contains("Teleported"));
+
+        let modes =
+            |names: &str| format!(r#"{{ "version": 1, "program": {{ "modes": [{names}] }} }}"#);
+        let err = load(&modes(
+            r#"{ "name": "main", "rules": [] }, { "name": "main", "rules": [] }"#,
+        ))
+        .unwrap_err();
+        assert!(err.contains("'main' already exists"));
+
+        let err = load(&modes(r#"{ "name": "a//b", "rules": [] }"#)).unwrap_err();
+        assert!(err.contains("Invalid mode name 'a//b'"));
+        assert!(load(&modes(r#"{ "name": "two words", "rules": [] }"#)).is_err());
+
+        // nor can a mode be switched to by a name it couldn't have
+        let err = load(&modes(
+            r#"{
+                "name": "main",
+                "rules": [{ "condition": { "Sensor": "HitWall" }, "command": { "GoToMode": "a b" } }]
+            }"#,
+        ))
+        .unwrap_err();
+        assert!(err.contains("Invalid mode name 'a b'"));
+    }
+}
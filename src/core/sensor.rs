@@ -1,21 +1,137 @@
 use bevy_egui::egui::WidgetText;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::core::{
+    dir::Dir,
+    register::{Counter, Flag},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Sensor {
     HitWall,
     SpaceLeft,
     SpaceRight,
+    SpaceAhead,
+    SpaceBehind,
+    WallAhead,
+    DirtAhead,
+    DirtLeft,
+    DirtRight,
+    /// the tile to the left has been visited before during this run
+    VisitedLeft,
+    VisitedRight,
+    VisitedAhead,
+    FacingNorth,
+    FacingEast,
+    FacingSouth,
+    FacingWest,
+    FlagSet(Flag),
+    /// the counter's value is greater than the given number
+    CounterAbove(Counter, u32),
+}
+
+impl Sensor {
+    /// every sensor, with counter thresholds starting out at 0
+    pub const ALL: [Sensor; 22] = [
+        Sensor::HitWall,
+        Sensor::SpaceLeft,
+        Sensor::SpaceRight,
+        Sensor::SpaceAhead,
+        Sensor::SpaceBehind,
+        Sensor::WallAhead,
+        Sensor::DirtAhead,
+        Sensor::DirtLeft,
+        Sensor::DirtRight,
+        Sensor::VisitedLeft,
+        Sensor::VisitedRight,
+        Sensor::VisitedAhead,
+        Sensor::FacingNorth,
+        Sensor::FacingEast,
+        Sensor::FacingSouth,
+        Sensor::FacingWest,
+        Sensor::FlagSet(Flag::A),
+        Sensor::FlagSet(Flag::B),
+        Sensor::FlagSet(Flag::C),
+        Sensor::FlagSet(Flag::D),
+        Sensor::CounterAbove(Counter::X, 0),
+        Sensor::CounterAbove(Counter::Y, 0),
+    ];
+
+    /// the sensor as it's written inside a condition, eg. "hit wall"
+    pub fn name(&self) -> String {
+        let name = match self {
+            Sensor::HitWall => "hit wall",
+            Sensor::SpaceLeft => "space left",
+            Sensor::SpaceRight => "space right",
+            Sensor::SpaceAhead => "space ahead",
+            Sensor::SpaceBehind => "space behind",
+            Sensor::WallAhead => "wall ahead",
+            Sensor::DirtAhead => "dirt ahead",
+            Sensor::DirtLeft => "dirt left",
+            Sensor::DirtRight => "dirt right",
+            Sensor::VisitedLeft => "visited left",
+            Sensor::VisitedRight => "visited right",
+            Sensor::VisitedAhead => "visited ahead",
+            Sensor::FacingNorth => "facing north",
+            Sensor::FacingEast => "facing east",
+            Sensor::FacingSouth => "facing south",
+            Sensor::FacingWest => "facing west",
+            Sensor::FlagSet(flag) => return format!("flag {} set", flag.name()),
+            Sensor::CounterAbove(counter, value) => {
+                return format!("counter {} above {value}", counter.name());
+            }
+        };
+        name.into()
+    }
+
+    /// the sensor that's active while the vac faces the given direction
+    pub fn facing(dir: Dir) -> Self {
+        match dir {
+            Dir::North => Sensor::FacingNorth,
+            Dir::East => Sensor::FacingEast,
+            Dir::South => Sensor::FacingSouth,
+            Dir::West => Sensor::FacingWest,
+        }
+    }
+}
+
+/// Everything the vac senses at the start of a tick
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Readings {
+    /// the sensors that are active, apart from the counter thresholds
+    active: Vec<Sensor>,
+    counters: [u32; Counter::ALL.len()],
+}
+
+impl Readings {
+    pub fn new(active: impl Into<Vec<Sensor>>) -> Self {
+        Self {
+            active: active.into(),
+            counters: [0; Counter::ALL.len()],
+        }
+    }
+
+    pub fn with_counter(mut self, counter: Counter, value: u32) -> Self {
+        self.counters[counter.idx()] = value;
+        self
+    }
+
+    pub fn is_active(&self, sensor: Sensor) -> bool {
+        match sensor {
+            Sensor::CounterAbove(counter, threshold) => self.counters[counter.idx()] > threshold,
+            _ => self.active.contains(&sensor),
+        }
+    }
+
+    pub fn active(&self) -> &[Sensor] {
+        &self.active
+    }
 }
 
 // FIXME: should this live somewhere else? it isn't purely about game logic
 impl From<Sensor> for String {
     fn from(val: Sensor) -> Self {
-        match val {
-            Sensor::HitWall => "WHEN hit wall",
-            Sensor::SpaceLeft => "WHEN space left",
-            Sensor::SpaceRight => "WHEN space right",
-        }
-        .into()
+        format!("WHEN {}", val.name())
     }
 }
 
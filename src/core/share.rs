@@ -0,0 +1,158 @@
+//! Short codes that pack a program, and optionally the map it's meant for, into a string
+//! that can be pasted into a chat or a URL. A code is the URL-safe base64 of:
+//!
+//! - the format version (1 byte)
+//! - flags, saying whether there's a map (1 byte)
+//! - a checksum of the uncompressed text (4 bytes)
+//! - the program in the text format, followed by a NUL and the map if there is one,
+//!   compressed with deflate
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+use crate::core::{
+    dsl,
+    map::{Map, MapParseError},
+    program::Program,
+};
+
+/// version of the format that `encode` writes
+pub const VERSION: u8 = 1;
+
+const HAS_MAP: u8 = 1;
+const HEADER_LEN: usize = 6;
+/// codes won't unpack into more than this, so a bad one can't eat up all the memory
+const MAX_TEXT_LEN: usize = 64 * 1024;
+
+/// What a share code unpacks into
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shared {
+    pub program: Program,
+    pub map: Option<Map>,
+}
+
+pub fn encode(program: &Program, map: Option<&Map>) -> String {
+    let mut text = dsl::print(program);
+    if let Some(map) = map {
+        text.push('\0');
+        text.push_str(&map.to_string());
+    }
+
+    let flags = if map.is_some() { HAS_MAP } else { 0 };
+    let mut bytes = vec![VERSION, flags];
+    bytes.extend(checksum(text.as_bytes()).to_be_bytes());
+    bytes.extend(miniz_oxide::deflate::compress_to_vec(text.as_bytes(), 9));
+
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn decode(code: &str) -> Result<Shared, String> {
+    let invalid = || "Not a valid share code".to_string();
+
+    let bytes = URL_SAFE_NO_PAD.decode(code.trim()).map_err(|_| invalid())?;
+    let Some((header, compressed)) = bytes.split_first_chunk::<HEADER_LEN>() else {
+        return Err(invalid());
+    };
+    let [version, flags, sum @ ..] = *header;
+
+    if version == 0 || version > VERSION {
+        return Err(format!(
+            "Share code version {version} isn't supported, only versions up to {VERSION} are"
+        ));
+    }
+
+    let text = miniz_oxide::inflate::decompress_to_vec_with_limit(compressed, MAX_TEXT_LEN)
+        .map_err(|_| invalid())?;
+    if checksum(&text) != u32::from_be_bytes(sum) {
+        return Err("Share code is damaged, it may not have been copied in full".into());
+    }
+    let text = String::from_utf8(text).map_err(|_| invalid())?;
+
+    let (program, map) = if flags & HAS_MAP != 0 {
+        let (program, map) = text.split_once('\0').ok_or_else(invalid)?;
+        (program, Some(map))
+    } else {
+        (text.as_str(), None)
+    };
+
+    let program =
+        dsl::parse(program).map_err(|err| format!("Invalid program in share code: {err}"))?;
+    let map = map
+        .map(|map| {
+            let map = Map::parse(map)?;
+            map.check_enclosed()?;
+            Ok(map)
+        })
+        .transpose()
+        .map_err(|err: MapParseError| format!("Invalid map in share code: {err}"))?;
+
+    Ok(Shared { program, map })
+}
+
+/// 32-bit FNV-1a, which is plenty to catch codes that got cut short or mistyped
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = r"WHEN space right AND NOT visited right THEN turn right
+WHEN hit wall THEN go to mode stuck
+OTHERWISE go forward
+MODE stuck
+DISABLED WHEN counter x above 3 THEN beep";
+
+    #[test]
+    fn round_trip() {
+        let program = dsl::parse(PROGRAM).unwrap();
+        let map = Map::parse(Map::BIG_LOOP_5X6).unwrap();
+
+        let code = encode(&program, None);
+        let shared = decode(&code).unwrap();
+        assert_eq!(shared.program, program);
+        assert_eq!(shared.map, None);
+
+        let code = encode(&program, Some(&map));
+        let shared = decode(&code).unwrap();
+        assert_eq!(shared.program, program);
+        assert_eq!(shared.map, Some(map));
+
+        // codes are safe to put in a URL as they are
+        assert!(
+            code.chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_')
+        );
+    }
+
+    #[test]
+    fn round_trip_deleted_mode() {
+        // the rule that switched to the deleted mode stays behind, and does nothing
+        let mut program = dsl::parse(PROGRAM).unwrap();
+        program.remove_mode(program.mode_idx("stuck").unwrap());
+
+        let code = encode(&program, None);
+        assert_eq!(decode(&code).unwrap().program, program);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let code = encode(&dsl::parse(PROGRAM).unwrap(), None);
+
+        assert!(decode("").is_err());
+        assert!(decode("not a code!").is_err());
+        assert!(decode(&code[..code.len() - 4]).is_err());
+
+        // flip a bit in the checksum
+        let mut bytes = URL_SAFE_NO_PAD.decode(&code).unwrap();
+        bytes[2] ^= 1;
+        let err = decode(&URL_SAFE_NO_PAD.encode(&bytes)).unwrap_err();
+        assert!(err.contains("damaged"));
+
+        bytes[0] = VERSION + 1;
+        let err = decode(&URL_SAFE_NO_PAD.encode(&bytes)).unwrap_err();
+        assert!(err.contains("isn't supported"));
+    }
+}
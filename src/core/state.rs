@@ -1,120 +1,352 @@
+use std::collections::{HashMap, HashSet};
+
 use bevy::math::IVec2;
 
-use crate::core::{command::Command, dir::Dir, map::Map, rule::Rule, sensor::Sensor};
+use crate::core::{
+    command::{Category, Command},
+    dir::Dir,
+    map::Map,
+    physics::Physics,
+    program::{DefaultAction, Mode, Program},
+    register::{Counter, Flag},
+    rule::Rule,
+    sensor::{Readings, Sensor},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Effect {
-    Moved { from: IVec2, to: IVec2 },
-    BumpedWall,
-    Rotated { from: Dir, to: Dir },
+    Moved {
+        from: IVec2,
+        to: IVec2,
+    },
+    /// moved onto a dirty tile and cleaned it
+    Cleaned {
+        from: IVec2,
+        to: IVec2,
+    },
+    /// tried to move in the given direction but there was a wall in the way
+    BumpedWall {
+        dir: Dir,
+    },
+    Rotated {
+        from: Dir,
+        to: Dir,
+    },
+    /// turned 180 degrees
+    TurnedAround {
+        from: Dir,
+        to: Dir,
+    },
+    /// a tool cleaned a dirty tile next to the vac
+    Swept {
+        at: IVec2,
+    },
+    FlagChanged {
+        flag: Flag,
+        set: bool,
+    },
+    /// a counter was incremented to the given value
+    Counted {
+        counter: Counter,
+        value: u32,
+    },
+    Beeped,
+    Waited,
+    /// switched from one mode of the program to another, given by their indices
+    ModeChanged {
+        from: usize,
+        to: usize,
+    },
+}
+
+impl Effect {
+    /// whether the effect counts against the score as a bump
+    pub fn is_bump(&self) -> bool {
+        match self {
+            Effect::BumpedWall { .. } => true,
+            Effect::Moved { .. }
+            | Effect::Cleaned { .. }
+            | Effect::Rotated { .. }
+            | Effect::TurnedAround { .. }
+            | Effect::Swept { .. }
+            | Effect::FlagChanged { .. }
+            | Effect::Counted { .. }
+            | Effect::Beeped
+            | Effect::Waited
+            | Effect::ModeChanged { .. } => false,
+        }
+    }
+}
+
+/// The parts of a `State` that decide how it evolves from here on, used to detect loops.
+/// Tiles only ever get cleaned or visited, so the number of cleaned and visited tiles stand
+/// in for the whole sets. Sensors only care whether a tile has been visited, not how often,
+/// so the visit counts themselves are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Snapshot {
+    vac_pos: IVec2,
+    vac_dir: Dir,
+    cleaned: usize,
+    visited: usize,
+    flags: [bool; Flag::ALL.len()],
+    counters: [u32; Counter::ALL.len()],
+    mode: usize,
+    hit_wall_last_tick: bool,
+    turn_cooldown: u32,
+    stunned: bool,
 }
 
+#[derive(Debug, Clone)]
 pub struct State {
     vac_pos: IVec2,
     vac_dir: Dir,
 
+    /// dirty tiles from the map that the vac has already cleaned
+    cleaned: HashSet<IVec2>,
+    /// how many times the vac has moved onto each tile, with the start counting as a visit
+    visits: HashMap<IVec2, usize>,
+
+    flags: [bool; Flag::ALL.len()],
+    counters: [u32; Counter::ALL.len()],
+    /// index of the program mode whose rules are checked
+    mode: usize,
+
     hit_wall_last_tick: bool,
-    turned_last_tick: bool,
+    /// ticks left until the vac can turn again
+    turn_cooldown: u32,
+    /// the vac bumped into a wall and sits out the next tick
+    stunned: bool,
 }
 
 impl State {
     pub fn new(vac_pos: impl Into<IVec2>, vac_dir: Dir) -> Self {
+        let vac_pos = vac_pos.into();
+
         Self {
-            vac_pos: vac_pos.into(),
+            vac_pos,
             vac_dir,
 
+            cleaned: HashSet::new(),
+            visits: HashMap::from([(vac_pos, 1)]),
+
+            flags: [false; Flag::ALL.len()],
+            counters: [0; Counter::ALL.len()],
+            mode: 0,
+
             hit_wall_last_tick: false,
-            turned_last_tick: false,
+            turn_cooldown: 0,
+            stunned: false,
         }
     }
 
-    pub fn tick(&mut self, map: &Map, rules: &[Rule]) -> Effect {
-        let sensors = self.evaluate_sensors(map);
+    /// the state at the start of a run on the given map
+    pub fn at_start(map: &Map) -> Self {
+        Self::new(map.start(), map.start_dir())
+    }
 
-        // Save flag before resetting
-        let turned_last_tick = self.turned_last_tick;
+    /// runs one tick of the program, applying one command per category in category order,
+    /// and returns the effects in the order they happened
+    pub fn tick(&mut self, map: &Map, program: &Program, physics: Physics) -> Vec<Effect> {
+        let can_turn = self.turn_cooldown == 0;
+        self.turn_cooldown = self.turn_cooldown.saturating_sub(1);
 
-        self.reset_flags();
+        // a stunned vac does nothing, and only senses the bump once it comes to
+        if self.stunned {
+            self.stunned = false;
+            return vec![Effect::Waited];
+        }
 
-        let mut commands = Rule::compute_commands(rules, &sensors);
+        let readings = self.evaluate_sensors(map);
+        self.hit_wall_last_tick = false;
+
+        let rules = program.mode(self.mode).map(Mode::rules).unwrap_or_default();
+        let mut commands = Rule::compute_commands(rules, &readings);
+
+        if !can_turn {
+            commands.retain(|cmd| cmd.category() != Category::Rotation);
+        }
 
-        // Filter out turn commands if we turned last tick
-        if turned_last_tick {
-            commands.retain(|cmd| !matches!(cmd, Command::TurnLeft | Command::TurnRight));
-            // If filtering left us empty, fall back to MoveForward
-            if commands.is_empty() {
-                commands.push(Command::MoveForward);
+        commands.sort_by_key(Command::category);
+
+        let turned = commands
+            .iter()
+            .any(|cmd| cmd.category() == Category::Rotation);
+        if turned {
+            self.turn_cooldown = physics.turn_cooldown;
+
+            if !physics.turn_and_move {
+                commands.retain(|cmd| cmd.category() != Category::Movement);
+            }
+        }
+
+        // if no rule moved the vac, the default action takes the place of a movement
+        // command, unless the vac turned and turning and moving can't share a tick
+        let moved = commands
+            .iter()
+            .any(|cmd| cmd.category() == Category::Movement);
+        let mut default_action =
+            (!moved && (!turned || physics.turn_and_move)).then_some(program.default_action());
+
+        let mut effects = Vec::new();
+        for command in &commands {
+            if command.category() > Category::Movement
+                && let Some(action) = default_action.take()
+            {
+                effects.push(self.apply_default_action(action, map));
             }
+            effects.extend(self.apply_command(command, map, program));
+        }
+        if let Some(action) = default_action {
+            effects.push(self.apply_default_action(action, map));
         }
 
-        // HACK: until we expand to have categories, there will only ever
-        // be one command
-        assert_eq!(commands.len(), 1);
-        self.apply_command(commands[0], map)
+        self.stunned = physics.bump_costs_tick && self.hit_wall_last_tick;
+
+        effects
     }
 
-    fn reset_flags(&mut self) {
-        self.hit_wall_last_tick = false;
-        self.turned_last_tick = false;
+    fn apply_default_action(&mut self, action: DefaultAction, map: &Map) -> Effect {
+        match action {
+            DefaultAction::Forward => self.move_towards(self.vac_dir, map),
+            DefaultAction::Wait => Effect::Waited,
+            DefaultAction::Reverse => self.move_towards(self.vac_dir.reverse(), map),
+        }
     }
 
-    fn apply_command(&mut self, command: Command, map: &Map) -> Effect {
-        match command {
-            Command::MoveForward => {
-                let orig_pos = self.vac_pos;
-                // check for a wall collision
-                let dest = orig_pos + self.vac_dir.to_ivec();
-
-                if map.has_space(dest) {
-                    self.vac_pos = dest;
-                    Effect::Moved {
-                        from: orig_pos,
-                        to: self.vac_pos,
-                    }
-                } else {
-                    self.hit_wall_last_tick = true;
-                    Effect::BumpedWall
-                }
+    /// moves one tile in the given direction without changing heading
+    fn move_towards(&mut self, dir: Dir, map: &Map) -> Effect {
+        let orig_pos = self.vac_pos;
+        // check for a wall collision
+        let dest = orig_pos + dir.to_ivec();
+
+        if !map.has_space(dest) {
+            self.hit_wall_last_tick = true;
+            return Effect::BumpedWall { dir };
+        }
+
+        self.vac_pos = dest;
+        *self.visits.entry(dest).or_default() += 1;
+
+        if map.has_dirt(dest) && self.cleaned.insert(dest) {
+            Effect::Cleaned {
+                from: orig_pos,
+                to: self.vac_pos,
+            }
+        } else {
+            Effect::Moved {
+                from: orig_pos,
+                to: self.vac_pos,
             }
+        }
+    }
+
+    fn apply_command(&mut self, command: &Command, map: &Map, program: &Program) -> Vec<Effect> {
+        match command {
             Command::TurnRight => {
                 let orig_dir = self.vac_dir;
                 self.vac_dir = orig_dir.rotate_cw();
-                self.turned_last_tick = true;
-                Effect::Rotated {
+                vec![Effect::Rotated {
                     from: orig_dir,
                     to: self.vac_dir,
-                }
+                }]
             }
             Command::TurnLeft => {
                 let orig_dir = self.vac_dir;
                 self.vac_dir = orig_dir.rotate_ccw();
-                self.turned_last_tick = true;
-                Effect::Rotated {
+                vec![Effect::Rotated {
                     from: orig_dir,
                     to: self.vac_dir,
-                }
+                }]
+            }
+            Command::TurnAround => {
+                let orig_dir = self.vac_dir;
+                self.vac_dir = orig_dir.reverse();
+                vec![Effect::TurnedAround {
+                    from: orig_dir,
+                    to: self.vac_dir,
+                }]
+            }
+            Command::MoveBackward => vec![self.move_towards(self.vac_dir.reverse(), map)],
+            Command::Wait => vec![Effect::Waited],
+            Command::Vacuum => self.sweep(map, [self.vac_dir]),
+            Command::Brush => {
+                self.sweep(map, [self.vac_dir.rotate_ccw(), self.vac_dir.rotate_cw()])
+            }
+            &Command::SetFlag(flag) | &Command::ClearFlag(flag) => {
+                let set = matches!(command, Command::SetFlag(_));
+                self.flags[flag.idx()] = set;
+                vec![Effect::FlagChanged { flag, set }]
+            }
+            &Command::Increment(counter) => {
+                let value = &mut self.counters[counter.idx()];
+                *value = value.saturating_add(1);
+                vec![Effect::Counted {
+                    counter,
+                    value: *value,
+                }]
             }
+            Command::GoToMode(name) => {
+                // switching to a mode that doesn't exist (anymore) does nothing
+                let Some(to) = program.mode_idx(name) else {
+                    return Vec::new();
+                };
+                let from = std::mem::replace(&mut self.mode, to);
+                vec![Effect::ModeChanged { from, to }]
+            }
+            Command::Beep => vec![Effect::Beeped],
         }
     }
 
-    fn evaluate_sensors(&self, map: &Map) -> Vec<Sensor> {
+    /// cleans any dirt on the tiles next to the vac in the given directions
+    fn sweep(&mut self, map: &Map, dirs: impl IntoIterator<Item = Dir>) -> Vec<Effect> {
+        dirs.into_iter()
+            .map(|dir| self.vac_pos + dir.to_ivec())
+            .filter(|pt| map.has_dirt(*pt) && self.cleaned.insert(*pt))
+            .map(|at| Effect::Swept { at })
+            .collect()
+    }
+
+    fn evaluate_sensors(&self, map: &Map) -> Readings {
         let mut sensors = Vec::new();
 
         let left = self.vac_pos + self.vac_dir.rotate_ccw().to_ivec();
-        if map.has_space(left) {
-            sensors.push(Sensor::SpaceLeft);
-        }
-
         let right = self.vac_pos + self.vac_dir.rotate_cw().to_ivec();
-        if map.has_space(right) {
-            sensors.push(Sensor::SpaceRight);
+        let ahead = self.vac_pos + self.vac_dir.to_ivec();
+        let behind = self.vac_pos + self.vac_dir.reverse().to_ivec();
+
+        let readings = [
+            (Sensor::HitWall, self.hit_wall_last_tick),
+            (Sensor::SpaceLeft, map.has_space(left)),
+            (Sensor::SpaceRight, map.has_space(right)),
+            (Sensor::SpaceAhead, map.has_space(ahead)),
+            (Sensor::SpaceBehind, map.has_space(behind)),
+            (Sensor::WallAhead, !map.has_space(ahead)),
+            (Sensor::DirtAhead, self.is_dirty(map, ahead)),
+            (Sensor::DirtLeft, self.is_dirty(map, left)),
+            (Sensor::DirtRight, self.is_dirty(map, right)),
+            (Sensor::VisitedLeft, self.visits(left) > 0),
+            (Sensor::VisitedRight, self.visits(right) > 0),
+            (Sensor::VisitedAhead, self.visits(ahead) > 0),
+        ];
+        for (sensor, active) in readings {
+            if active {
+                sensors.push(sensor);
+            }
         }
 
-        if self.hit_wall_last_tick {
-            sensors.push(Sensor::HitWall);
+        sensors.push(Sensor::facing(self.vac_dir));
+
+        for flag in Flag::ALL {
+            if self.flag(flag) {
+                sensors.push(Sensor::FlagSet(flag));
+            }
         }
 
-        sensors
+        Counter::ALL
+            .into_iter()
+            .fold(Readings::new(sensors), |readings, counter| {
+                readings.with_counter(counter, self.counter(counter))
+            })
     }
 
     pub fn vac_pos(&self) -> IVec2 {
@@ -124,11 +356,62 @@ impl State {
     pub fn vac_dir(&self) -> Dir {
         self.vac_dir
     }
+
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            vac_pos: self.vac_pos,
+            vac_dir: self.vac_dir,
+            cleaned: self.cleaned.len(),
+            visited: self.visits.len(),
+            flags: self.flags,
+            counters: self.counters,
+            mode: self.mode,
+            hit_wall_last_tick: self.hit_wall_last_tick,
+            turn_cooldown: self.turn_cooldown,
+            stunned: self.stunned,
+        }
+    }
+
+    pub fn flag(&self, flag: Flag) -> bool {
+        self.flags[flag.idx()]
+    }
+
+    pub fn counter(&self, counter: Counter) -> u32 {
+        self.counters[counter.idx()]
+    }
+
+    /// index of the program mode the vac is in
+    pub fn mode(&self) -> usize {
+        self.mode
+    }
+
+    /// how many times the vac has been on the tile
+    pub fn visits(&self, pt: impl Into<IVec2>) -> usize {
+        self.visits.get(&pt.into()).copied().unwrap_or(0)
+    }
+
+    /// visit counts of every tile the vac has been on
+    pub fn visit_counts(&self) -> &HashMap<IVec2, usize> {
+        &self.visits
+    }
+
+    pub fn is_dirty(&self, map: &Map, pt: impl Into<IVec2>) -> bool {
+        let pt = pt.into();
+        map.has_dirt(pt) && !self.cleaned.contains(&pt)
+    }
+
+    /// number of dirty tiles on the map that haven't been cleaned yet
+    pub fn remaining_dirt(&self, map: &Map) -> usize {
+        map.dirt()
+            .iter()
+            .filter(|pt| !self.cleaned.contains(*pt))
+            .count()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::core::rule::Rule;
+    use crate::core::{condition::Condition, dsl, rule::Rule};
 
     use super::*;
 
@@ -139,13 +422,46 @@ mod tests {
 
         // given that setup, there should be space on the left but not on the right
         let sensors = state.evaluate_sensors(&map);
-        assert!(sensors.contains(&Sensor::SpaceLeft));
-        assert!(!sensors.contains(&Sensor::SpaceRight));
-        assert!(!sensors.contains(&Sensor::HitWall));
+        assert!(sensors.is_active(Sensor::SpaceLeft));
+        assert!(!sensors.is_active(Sensor::SpaceRight));
+        assert!(!sensors.is_active(Sensor::HitWall));
 
         state.hit_wall_last_tick = true;
         let sensors = state.evaluate_sensors(&map);
-        assert!(sensors.contains(&Sensor::HitWall));
+        assert!(sensors.is_active(Sensor::HitWall));
+    }
+
+    #[test]
+    fn test_evaluate_proactive_sensors() {
+        let map = Map::parse(Map::DIRTY_ROOM_4X4).unwrap();
+        let mut state = State::new((1, 1), Dir::North);
+
+        let sensors = state.evaluate_sensors(&map);
+        assert_eq!(
+            sensors.active(),
+            [
+                Sensor::SpaceRight,
+                Sensor::SpaceAhead,
+                Sensor::DirtAhead,
+                Sensor::DirtRight,
+                Sensor::FacingNorth
+            ]
+        );
+
+        // after moving up a tile and turning around, the start tile is ahead
+        state.apply_default_action(DefaultAction::Forward, &map);
+        state.vac_dir = Dir::South;
+        let sensors = state.evaluate_sensors(&map);
+        assert_eq!(
+            sensors.active(),
+            [
+                Sensor::SpaceLeft,
+                Sensor::SpaceAhead,
+                Sensor::DirtLeft,
+                Sensor::VisitedAhead,
+                Sensor::FacingSouth
+            ]
+        );
     }
 
     #[test]
@@ -153,7 +469,7 @@ mod tests {
         let map = Map::parse(Map::EMPTY_3X3).unwrap();
         let mut state = State::new((0, 0), Dir::East);
 
-        let effect = state.apply_command(Command::MoveForward, &map);
+        let effect = state.apply_default_action(DefaultAction::Forward, &map);
         assert_eq!(state.vac_pos, (1, 0).into());
         assert_eq!(state.vac_dir, Dir::East);
         assert_eq!(
@@ -164,26 +480,26 @@ mod tests {
             }
         );
 
-        let effect = state.apply_command(Command::TurnRight, &map);
+        let effects = state.apply_command(&Command::TurnRight, &map, &Program::default());
         assert_eq!(state.vac_pos, (1, 0).into());
         assert_eq!(state.vac_dir, Dir::South);
         assert_eq!(
-            effect,
-            Effect::Rotated {
+            effects,
+            vec![Effect::Rotated {
                 from: Dir::East,
                 to: Dir::South
-            }
+            }]
         );
 
-        let effect = state.apply_command(Command::TurnLeft, &map);
+        let effects = state.apply_command(&Command::TurnLeft, &map, &Program::default());
         assert_eq!(state.vac_pos, (1, 0).into());
         assert_eq!(state.vac_dir, Dir::East);
         assert_eq!(
-            effect,
-            Effect::Rotated {
+            effects,
+            vec![Effect::Rotated {
                 from: Dir::South,
                 to: Dir::East
-            }
+            }]
         );
     }
 
@@ -193,7 +509,7 @@ mod tests {
         let mut state = State::new((1, 1), Dir::East);
 
         // there's one space to move to in this direction before we hit a wall
-        let effect = state.apply_command(Command::MoveForward, &map);
+        let effect = state.apply_default_action(DefaultAction::Forward, &map);
         assert_eq!(state.vac_pos, (2, 1).into());
         assert!(!state.hit_wall_last_tick);
         assert_eq!(
@@ -205,10 +521,50 @@ mod tests {
         );
 
         // shouldn't be able to move forward again
-        let effect = state.apply_command(Command::MoveForward, &map);
+        let effect = state.apply_default_action(DefaultAction::Forward, &map);
         assert_eq!(state.vac_pos, (2, 1).into());
         assert!(state.hit_wall_last_tick);
-        assert_eq!(effect, Effect::BumpedWall);
+        assert_eq!(effect, Effect::BumpedWall { dir: Dir::East });
+    }
+
+    #[test]
+    fn test_apply_commands_cleaning() {
+        let map = Map::parse(Map::DIRTY_ROOM_4X4).unwrap();
+        let mut state = State::new((1, 1), Dir::East);
+        assert_eq!(state.remaining_dirt(&map), 3);
+
+        // moving onto a dirty tile cleans it
+        let effect = state.apply_default_action(DefaultAction::Forward, &map);
+        assert_eq!(
+            effect,
+            Effect::Cleaned {
+                from: (1, 1).into(),
+                to: (2, 1).into()
+            }
+        );
+        assert!(!state.is_dirty(&map, (2, 1)));
+        assert_eq!(state.remaining_dirt(&map), 2);
+
+        // coming back to a cleaned tile is a plain move
+        state.vac_pos = (1, 1).into();
+        let effect = state.apply_default_action(DefaultAction::Forward, &map);
+        assert_eq!(
+            effect,
+            Effect::Moved {
+                from: (1, 1).into(),
+                to: (2, 1).into()
+            }
+        );
+        assert_eq!(state.remaining_dirt(&map), 2);
+    }
+
+    #[test]
+    fn test_at_start() {
+        let map = Map::parse("####\n#.v#\n####").unwrap();
+        let state = State::at_start(&map);
+
+        assert_eq!(state.vac_pos(), (2, 1).into());
+        assert_eq!(state.vac_dir(), Dir::South);
     }
 
     #[test]
@@ -220,17 +576,18 @@ mod tests {
             Rule::new(Sensor::SpaceRight, Command::TurnRight),
             Rule::new(Sensor::SpaceLeft, Command::TurnLeft),
         ];
+        let program = Program::new(rules);
 
         // there's space on the left but not the right,
         // so we should turn left
-        let effect = state.tick(&map, &rules);
+        let effects = state.tick(&map, &program, Physics::default());
         assert_eq!(state.vac_dir, Dir::North);
         assert_eq!(
-            effect,
-            Effect::Rotated {
+            effects,
+            vec![Effect::Rotated {
                 from: Dir::East,
                 to: Dir::North
-            }
+            }]
         );
     }
 
@@ -241,17 +598,352 @@ mod tests {
 
         // Rule that always tries to turn
         let rules = [Rule::new(Sensor::SpaceLeft, Command::TurnRight)];
+        let program = Program::new(rules);
 
         // First tick should turn
-        let effect = state.tick(&map, &rules);
-        assert!(matches!(effect, Effect::Rotated { .. }));
+        let effects = state.tick(&map, &program, Physics::default());
+        assert!(matches!(effects[..], [Effect::Rotated { .. }]));
 
         // Second tick should move forward (restriction enforced)
-        let effect = state.tick(&map, &rules);
-        assert!(!matches!(effect, Effect::Rotated { .. }));
+        let effects = state.tick(&map, &program, Physics::default());
+        assert!(!matches!(effects[..], [Effect::Rotated { .. }]));
 
         // Third tick can turn again
-        let effect = state.tick(&map, &rules);
-        assert!(matches!(effect, Effect::Rotated { .. }));
+        let effects = state.tick(&map, &program, Physics::default());
+        assert!(matches!(effects[..], [Effect::Rotated { .. }]));
+    }
+
+    #[test]
+    fn test_tools() {
+        let map = Map::parse("#####\n#*.*#\n#*S*#\n#####").unwrap();
+        let mut state = State::at_start(&map);
+
+        // the tile ahead is dirty, the tiles to the sides aren't
+        let effects = state.apply_command(&Command::Brush, &map, &Program::default());
+        assert!(effects.is_empty());
+
+        let effects = state.apply_command(&Command::Vacuum, &map, &Program::default());
+        assert_eq!(effects, vec![Effect::Swept { at: (3, 1).into() }]);
+        assert!(!state.is_dirty(&map, (3, 1)));
+
+        // from the middle of the top row the brush reaches both corners
+        state.vac_pos = (2, 2).into();
+        state.vac_dir = Dir::North;
+        let effects = state.apply_command(&Command::Brush, &map, &Program::default());
+        assert_eq!(
+            effects,
+            vec![
+                Effect::Swept { at: (1, 2).into() },
+                Effect::Swept { at: (3, 2).into() }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tick_multiple_categories() {
+        let map = Map::parse(Map::DIRTY_ROOM_4X4).unwrap();
+        let mut state = State::new((1, 1), Dir::North);
+
+        let rules = [
+            Rule::new(Sensor::SpaceRight, Command::Beep),
+            Rule::new(Sensor::SpaceRight, Command::Vacuum),
+            Rule::new(Sensor::SpaceRight, Command::TurnRight),
+        ];
+        let program = Program::new(rules);
+
+        // commands are applied in category order rather than rule order, so the vac turns
+        // to face the dirt before vacuuming it, and beeps last
+        let effects = state.tick(&map, &program, Physics::default());
+        assert_eq!(
+            effects,
+            vec![
+                Effect::Rotated {
+                    from: Dir::North,
+                    to: Dir::East
+                },
+                Effect::Swept { at: (2, 1).into() },
+                Effect::Beeped,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_actions() {
+        let map = Map::parse(Map::CORRIDOR_5X3).unwrap();
+        let rules = [Rule::new(Sensor::HitWall, Command::Beep)];
+
+        let mut state = State::at_start(&map);
+        let program = Program::default().with_default_action(DefaultAction::Wait);
+        assert_eq!(
+            state.tick(&map, &program, Physics::default()),
+            vec![Effect::Waited]
+        );
+        assert_eq!(state.vac_pos(), map.start());
+
+        // backing into the wall behind the start sets off the hit wall sensor
+        let program = Program::new(rules).with_default_action(DefaultAction::Reverse);
+        assert_eq!(
+            state.tick(&map, &program, Physics::default()),
+            vec![Effect::BumpedWall { dir: Dir::West }]
+        );
+        assert_eq!(
+            state.tick(&map, &program, Physics::default()),
+            vec![Effect::BumpedWall { dir: Dir::West }, Effect::Beeped]
+        );
+        assert_eq!(state.vac_dir(), Dir::East);
+    }
+
+    #[test]
+    fn test_reverse_commands() {
+        let map = Map::parse(Map::CORRIDOR_5X3).unwrap();
+        let mut state = State::at_start(&map);
+        let start = map.start();
+        let program = Program::default();
+
+        // backing into the wall behind the start bumps it like driving into it would
+        let effects = state.apply_command(&Command::MoveBackward, &map, &program);
+        assert_eq!(effects, vec![Effect::BumpedWall { dir: Dir::West }]);
+        assert!(state.hit_wall_last_tick);
+
+        let effects = state.apply_command(&Command::TurnAround, &map, &program);
+        assert_eq!(
+            effects,
+            vec![Effect::TurnedAround {
+                from: Dir::East,
+                to: Dir::West
+            }]
+        );
+
+        let effects = state.apply_command(&Command::MoveBackward, &map, &program);
+        assert_eq!(
+            effects,
+            vec![Effect::Moved {
+                from: start,
+                to: start + IVec2::X
+            }]
+        );
+        assert_eq!(state.vac_dir(), Dir::West);
+
+        let effects = state.apply_command(&Command::Wait, &map, &program);
+        assert_eq!(effects, vec![Effect::Waited]);
+        assert_eq!(state.vac_pos(), start + IVec2::X);
+    }
+
+    #[test]
+    fn test_movement_commands() {
+        let map = Map::parse(Map::CORRIDOR_5X3).unwrap();
+        let program = Program::new([
+            Rule::new(Sensor::SpaceAhead, Command::MoveBackward),
+            Rule::new(Sensor::HitWall, Command::TurnAround),
+            Rule::new(Sensor::HitWall, Command::Wait),
+        ]);
+        let bumped = Effect::BumpedWall { dir: Dir::West };
+        let turned = Effect::TurnedAround {
+            from: Dir::East,
+            to: Dir::West,
+        };
+
+        // backing up takes the place of the default action, and turning around rules out
+        // moving on the same tick
+        let mut state = State::at_start(&map);
+        let physics = Physics::default();
+        assert_eq!(state.tick(&map, &program, physics), vec![bumped]);
+        assert_eq!(state.tick(&map, &program, physics), vec![turned]);
+        assert_eq!(state.vac_pos(), map.start());
+
+        let mut state = State::at_start(&map);
+        let physics = physics.with_turn_and_move(true);
+        assert_eq!(state.tick(&map, &program, physics), vec![bumped]);
+        assert_eq!(
+            state.tick(&map, &program, physics),
+            vec![
+                turned,
+                Effect::Moved {
+                    from: map.start(),
+                    to: map.start() + IVec2::X
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_visit_counts() {
+        let map = Map::parse(Map::CORRIDOR_5X3).unwrap();
+        let mut state = State::at_start(&map);
+        assert_eq!(state.visits(map.start()), 1);
+
+        // shuttle back and forth between the first two tiles
+        state.apply_default_action(DefaultAction::Forward, &map);
+        state.apply_default_action(DefaultAction::Reverse, &map);
+        state.apply_default_action(DefaultAction::Forward, &map);
+        state.apply_default_action(DefaultAction::Forward, &map);
+
+        assert_eq!(state.visits((1, 1)), 2);
+        assert_eq!(state.visits((2, 1)), 2);
+        assert_eq!(state.visits((3, 1)), 1);
+        assert_eq!(state.visits((0, 1)), 0);
+        assert_eq!(state.visit_counts().len(), 3);
+
+        // the vac is on the exit now, with the outer wall ahead of it
+        let sensors = state.evaluate_sensors(&map);
+        assert!(!sensors.is_active(Sensor::VisitedAhead));
+
+        state.vac_pos = (2, 1).into();
+        state.vac_dir = Dir::North;
+        let sensors = state.evaluate_sensors(&map);
+        assert!(sensors.is_active(Sensor::VisitedLeft));
+        assert!(sensors.is_active(Sensor::VisitedRight));
+    }
+
+    #[test]
+    fn test_registers() {
+        let map = Map::parse(Map::BIG_LOOP_5X6).unwrap();
+        let mut state = State::at_start(&map);
+
+        // count the turns, and stop turning after the second one
+        let rules = [
+            Rule::with_condition(
+                Condition::parse("hit wall AND NOT flag a set").unwrap(),
+                Command::TurnLeft,
+            ),
+            Rule::new(Sensor::HitWall, Command::Increment(Counter::X)),
+            Rule::new(
+                Sensor::CounterAbove(Counter::X, 1),
+                Command::SetFlag(Flag::A),
+            ),
+        ];
+        let program = Program::new(rules);
+
+        let mut effects = Vec::new();
+        for _ in 0..30 {
+            effects.extend(state.tick(&map, &program, Physics::default()));
+        }
+
+        let turns = effects
+            .iter()
+            .filter(|effect| matches!(effect, Effect::Rotated { .. }))
+            .count();
+        assert_eq!(turns, 2);
+        assert!(state.flag(Flag::A));
+        assert!(!state.flag(Flag::B));
+        assert!(state.counter(Counter::X) > 2);
+        assert!(effects.contains(&Effect::FlagChanged {
+            flag: Flag::A,
+            set: true
+        }));
+
+        let sensors = state.evaluate_sensors(&map);
+        assert!(sensors.is_active(Sensor::FlagSet(Flag::A)));
+        assert!(sensors.is_active(Sensor::CounterAbove(Counter::X, 2)));
+        assert!(!sensors.is_active(Sensor::CounterAbove(Counter::Y, 0)));
+
+        state.apply_command(&Command::ClearFlag(Flag::A), &map, &Program::default());
+        assert!(!state.flag(Flag::A));
+    }
+
+    #[test]
+    fn test_modes() {
+        let map = Map::parse(Map::BIG_LOOP_5X6).unwrap();
+        let mut state = State::at_start(&map);
+
+        // turn left at the first wall, then keep bumping into the next one
+        let program = dsl::parse(
+            r"WHEN hit wall THEN go to mode stuck
+WHEN hit wall THEN turn left
+MODE stuck
+WHEN hit wall THEN beep",
+        )
+        .unwrap();
+
+        let mut effects = Vec::new();
+        for _ in 0..30 {
+            effects.extend(state.tick(&map, &program, Physics::default()));
+        }
+
+        assert_eq!(state.mode(), 1);
+        let changes = effects
+            .iter()
+            .filter(|effect| matches!(effect, Effect::ModeChanged { .. }))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [&Effect::ModeChanged { from: 0, to: 1 }]);
+        assert!(effects.contains(&Effect::Beeped));
+
+        // going to a mode that doesn't exist is ignored
+        let effects = state.apply_command(&Command::GoToMode("gone".into()), &map, &program);
+        assert!(effects.is_empty());
+        assert_eq!(state.mode(), 1);
+    }
+
+    #[test]
+    fn test_turn_cooldown() {
+        let map = Map::parse(Map::ROOM_4X4).unwrap();
+        let program = Program::new([Rule::with_condition(
+            Condition::parse("always").unwrap(),
+            Command::TurnRight,
+        )]);
+
+        let turns = |physics: Physics| {
+            let mut state = State::new((1, 1), Dir::East);
+            (0..6)
+                .map(|_| {
+                    let effects = state.tick(&map, &program, physics);
+                    matches!(effects[..], [Effect::Rotated { .. }])
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let physics = Physics::default();
+        assert_eq!(turns(physics.with_turn_cooldown(0)), [true; 6]);
+        assert_eq!(turns(physics), [true, false, true, false, true, false]);
+        assert_eq!(
+            turns(physics.with_turn_cooldown(2)),
+            [true, false, false, true, false, false]
+        );
+    }
+
+    #[test]
+    fn test_turn_and_move() {
+        let map = Map::parse(Map::ROOM_4X4).unwrap();
+        let program = Program::new([Rule::new(Sensor::SpaceLeft, Command::TurnLeft)]);
+
+        let mut state = State::new((1, 1), Dir::East);
+        let effects = state.tick(&map, &program, Physics::default());
+        assert!(matches!(effects[..], [Effect::Rotated { .. }]));
+        assert_eq!(state.vac_pos(), (1, 1).into());
+
+        // the turn comes first, so the vac moves off in its new heading
+        let mut state = State::new((1, 1), Dir::East);
+        let physics = Physics::default().with_turn_and_move(true);
+        let effects = state.tick(&map, &program, physics);
+        assert!(matches!(
+            effects[..],
+            [Effect::Rotated { .. }, Effect::Moved { .. }]
+        ));
+        assert_eq!(state.vac_pos(), (1, 2).into());
+    }
+
+    #[test]
+    fn test_bump_costs_tick() {
+        let map = Map::parse(Map::ROOM_4X4).unwrap();
+        let program = Program::new([Rule::new(Sensor::HitWall, Command::Beep)]);
+        let bumped = || Effect::BumpedWall { dir: Dir::West };
+
+        let mut state = State::new((1, 1), Dir::West);
+        let physics = Physics::default();
+        assert_eq!(state.tick(&map, &program, physics), [bumped()]);
+        assert_eq!(
+            state.tick(&map, &program, physics),
+            [bumped(), Effect::Beeped]
+        );
+
+        // the vac sits out the tick after a bump, and senses it on the one after that
+        let mut state = State::new((1, 1), Dir::West);
+        let physics = physics.with_bump_costs_tick(true);
+        assert_eq!(state.tick(&map, &program, physics), [bumped()]);
+        assert_eq!(state.tick(&map, &program, physics), [Effect::Waited]);
+        assert_eq!(
+            state.tick(&map, &program, physics),
+            [bumped(), Effect::Beeped]
+        );
     }
 }
@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+
+use crate::{
+    core::{
+        level::{Goal, Level, Progress, Score},
+        map::Map,
+        physics::Physics,
+    },
+    game::{
+        map::MapSetup,
+        map_asset::{MapAsset, MapAssetLoader},
+    },
+};
+
+pub const DEFAULT_MAP: &str = "maps/corridors.map";
+
+/// Maps that ship with the game, relative to the assets folder
+const BUNDLED_MAPS: [&str; 3] = ["maps/big_loop.map", "maps/corridors.map", "maps/exit.map"];
+
+/// Where to look for extra maps on disk
+const MAPS_DIR: &str = "assets/maps";
+
+const TICK_BUDGET: usize = 100;
+
+/// what's shown in place of the map's path when it came with a share code
+const SHARED_MAP: &str = "shared map";
+
+/// The level currently being played
+#[derive(Resource, Deref)]
+pub struct CurrentLevel(pub Level);
+
+/// The constraints the vac moves under in levels whose map doesn't set its own, eg. as set
+/// on the command line
+#[derive(Resource, Deref)]
+pub struct LevelPhysics(pub Physics);
+
+/// Progress of the current run, updated by the vac on every tick
+#[derive(Resource, Deref, DerefMut)]
+pub struct LevelProgress(pub Progress);
+
+/// Sent once the vac meets the goal of the current level
+#[derive(Message, Debug)]
+pub struct LevelComplete {
+    pub score: Score,
+}
+
+/// Sent whenever the map and vac need to be set up from scratch for `CurrentLevel`: after
+/// a different map was selected, the map file changed on disk, or the run was reset
+#[derive(Message, Debug)]
+pub struct SetupLevel;
+
+/// The map file the current level is built from
+#[derive(Resource)]
+pub struct SelectedMap {
+    /// asset path of the map file, eg. "maps/corridors.map"
+    path: String,
+    handle: Option<Handle<MapAsset>>,
+    /// a map that came with a share code, played instead of a map file
+    shared: Option<Map>,
+
+    /// whether the level needs to be rebuilt once the map is loaded
+    dirty: bool,
+}
+
+impl SelectedMap {
+    fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            handle: None,
+            shared: None,
+            dirty: true,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn handle(&self) -> Option<&Handle<MapAsset>> {
+        self.handle.as_ref()
+    }
+
+    pub fn select(&mut self, path: impl Into<String>) {
+        *self = Self::new(path);
+    }
+
+    /// plays a map that isn't backed by a file, until another one is selected
+    pub fn select_shared(&mut self, map: Map) {
+        *self = Self {
+            shared: Some(map),
+            ..Self::new(SHARED_MAP)
+        };
+    }
+}
+
+/// Asset paths of the maps that can be picked in the UI
+#[derive(Resource, Deref)]
+pub struct AvailableMaps(Vec<String>);
+
+impl AvailableMaps {
+    /// the bundled maps, plus any others that have been dropped into the maps folder
+    fn find() -> Self {
+        let mut maps = BUNDLED_MAPS.map(String::from).to_vec();
+
+        // the maps folder can't be read on the web, so stick to the bundled maps there
+        if let Ok(entries) = std::fs::read_dir(MAPS_DIR) {
+            for entry in entries.flatten() {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+
+                let path = format!("maps/{name}");
+                if name.ends_with(".map") && !maps.contains(&path) {
+                    maps.push(path);
+                }
+            }
+        }
+
+        maps.sort();
+        Self(maps)
+    }
+}
+
+fn update_level(
+    mut commands: Commands,
+    mut selected: ResMut<SelectedMap>,
+    mut asset_events: MessageReader<AssetEvent<MapAsset>>,
+    mut setup_level: MessageWriter<SetupLevel>,
+    maps: Res<Assets<MapAsset>>,
+    server: Res<AssetServer>,
+    physics: Res<LevelPhysics>,
+) {
+    if let Some(map) = &selected.shared {
+        if selected.dirty {
+            commands.insert_resource(CurrentLevel(level_for(map, **physics)));
+            setup_level.write(SetupLevel);
+            selected.dirty = false;
+        }
+        return;
+    }
+
+    let handle = match &selected.handle {
+        Some(handle) => handle.clone(),
+        None => {
+            let handle = server.load(selected.path.clone());
+            selected.handle = Some(handle.clone());
+            handle
+        }
+    };
+
+    // rebuild the level whenever the map file is edited
+    if asset_events.read().any(|event| event.is_modified(&handle)) {
+        selected.dirty = true;
+    }
+
+    if !selected.dirty {
+        return;
+    }
+
+    let Some(map) = maps.get(&handle) else {
+        return;
+    };
+
+    selected.dirty = false;
+
+    commands.insert_resource(CurrentLevel(level_for(map, **physics)));
+    setup_level.write(SetupLevel);
+}
+
+fn level_for(map: &Map, physics: Physics) -> Level {
+    Level::new(map.clone(), Goal::default_for(map))
+        .with_tick_budget(TICK_BUDGET)
+        .with_physics(map.physics().apply(physics))
+}
+
+fn announce_level_complete(mut messages: MessageReader<LevelComplete>) {
+    for LevelComplete { score } in messages.read() {
+        info!(
+            "Level complete in {} ticks with {} bumps and {} rules: {} points",
+            score.ticks,
+            score.bumps,
+            score.rule_count,
+            score.points()
+        );
+    }
+}
+
+pub struct LevelPlugin {
+    /// asset path of the map to start with
+    pub map: String,
+    /// map to start with instead, when one came with a share code
+    pub shared_map: Option<Map>,
+    /// constraints the vac moves under, unless the level's map sets its own
+    pub physics: Physics,
+}
+
+impl Default for LevelPlugin {
+    fn default() -> Self {
+        Self {
+            map: DEFAULT_MAP.into(),
+            shared_map: None,
+            physics: Physics::default(),
+        }
+    }
+}
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        let mut selected = SelectedMap::new(self.map.clone());
+        if let Some(map) = &self.shared_map {
+            selected.select_shared(map.clone());
+        }
+
+        app.init_asset::<MapAsset>()
+            .init_asset_loader::<MapAssetLoader>()
+            .insert_resource(selected)
+            .insert_resource(LevelPhysics(self.physics))
+            .insert_resource(AvailableMaps::find())
+            .add_message::<SetupLevel>()
+            .add_message::<LevelComplete>()
+            .add_systems(
+                Update,
+                (update_level.before(MapSetup), announce_level_complete),
+            );
+    }
+}
@@ -1,6 +1,12 @@
 use bevy::prelude::*;
 
-use crate::{core::map::Map as CoreMap, game::constants::GRID_SIZE};
+use crate::{
+    core::map::Map as CoreMap,
+    game::{
+        constants::GRID_SIZE,
+        level::{CurrentLevel, SetupLevel},
+    },
+};
 
 #[derive(Debug, Component)]
 pub struct Map {
@@ -58,25 +64,36 @@ impl ToGameWorld for Vec2 {
     }
 }
 
-const MAP_STR: &str = r"#######
-#S..###
-#.#.###
-#.#...#
-#.#.#.#
-#.#...#
-#.###.#
-#.....#
-#######
-";
-
 pub const WALL_COLOR: Color = Color::hsl(0., 0.0, 0.3);
+pub const DIRT_COLOR: Color = Color::hsl(30., 0.4, 0.35);
+pub const EXIT_COLOR: Color = Color::hsl(120., 0.5, 0.3);
+
+/// Marks a dirt tile entity, storing its position on the map
+#[derive(Component, Deref)]
+pub struct Dirt(pub IVec2);
 
+/// (Re)spawns the map whenever the level is set up
 pub fn setup_map(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    mut setup_level: MessageReader<SetupLevel>,
+    level: Option<Res<CurrentLevel>>,
+    existing: Query<Entity, With<Map>>,
 ) {
-    let map = Map::new(CoreMap::parse(MAP_STR).unwrap());
+    if setup_level.read().count() == 0 {
+        return;
+    }
+
+    let Some(level) = level else {
+        return;
+    };
+
+    for entity in &existing {
+        commands.entity(entity).despawn();
+    }
+
+    let map = Map::new(level.map().clone());
 
     let wall_positions = map
         .walls()
@@ -84,7 +101,15 @@ pub fn setup_map(
         .map(|wall| map.to_game_world(*wall))
         .collect::<Vec<_>>();
 
-    // spawn map with wall tiles as children
+    let dirt_positions = map
+        .dirt()
+        .iter()
+        .map(|dirt| (*dirt, map.to_game_world(*dirt)))
+        .collect::<Vec<_>>();
+
+    let exit_pos = map.exit().map(|exit| map.to_game_world(exit));
+
+    // spawn map with wall, dirt and exit tiles as children
     commands
         .spawn((map, Transform::default(), Visibility::default()))
         .with_children(|parent| {
@@ -97,6 +122,27 @@ pub fn setup_map(
                     Transform::from_translation(wall_pos),
                 ));
             }
+
+            for (pos, dirt_pos) in dirt_positions {
+                let dirt = meshes.add(Rectangle::new(0.6 * GRID_SIZE, 0.6 * GRID_SIZE));
+
+                parent.spawn((
+                    Mesh2d(dirt),
+                    MeshMaterial2d(materials.add(DIRT_COLOR)),
+                    Transform::from_translation(dirt_pos.with_z(-0.05)),
+                    Dirt(pos),
+                ));
+            }
+
+            if let Some(exit_pos) = exit_pos {
+                let exit = meshes.add(Rectangle::new(GRID_SIZE, GRID_SIZE));
+
+                parent.spawn((
+                    Mesh2d(exit),
+                    MeshMaterial2d(materials.add(EXIT_COLOR)),
+                    Transform::from_translation(exit_pos.with_z(-0.05)),
+                ));
+            }
         });
 }
 
@@ -107,6 +153,6 @@ pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_map.in_set(MapSetup));
+        app.add_systems(Update, setup_map.in_set(MapSetup));
     }
 }
@@ -0,0 +1,39 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+
+use crate::core::map::Map as CoreMap;
+
+/// A map loaded from a `.map` file in the text format that `core::map::Map::parse` accepts
+#[derive(Asset, TypePath, Debug, Deref)]
+pub struct MapAsset(pub CoreMap);
+
+#[derive(Default)]
+pub struct MapAssetLoader;
+
+impl AssetLoader for MapAssetLoader {
+    type Asset = MapAsset;
+    type Settings = ();
+    type Error = BevyError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let str = String::from_utf8(bytes)?;
+        let map = CoreMap::parse(&str)?;
+        map.check_enclosed()?;
+
+        Ok(MapAsset(map))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map"]
+    }
+}
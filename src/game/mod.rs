@@ -1,4 +1,7 @@
 pub mod constants;
+pub mod level;
 pub mod map;
+pub mod map_asset;
 pub mod simulation;
 pub mod vac;
+pub mod visits;
@@ -1,20 +1,82 @@
 use bevy::prelude::*;
 
-#[derive(Default, Resource)]
+/// slowest and fastest playback speed, as a multiple of the normal speed
+pub const MIN_SPEED: f32 = 0.25;
+pub const MAX_SPEED: f32 = 50.;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SimState {
+    /// not started yet, or finished
+    #[default]
+    Stopped,
+    Running,
+    Paused,
+    /// running until the current tick has played out, then pausing again
+    Stepping,
+    /// running as many ticks per frame as possible, without animation, until the run is over
+    SkippingToEnd,
+}
+
+#[derive(Resource)]
 pub struct Simulation {
-    running: bool,
+    state: SimState,
+    speed: f32,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            state: SimState::default(),
+            speed: 1.,
+        }
+    }
 }
 
 impl Simulation {
+    pub fn state(&self) -> SimState {
+        self.state
+    }
+
+    /// whether the vac should be moving, either because the simulation is running or
+    /// because it's taking a single step
     pub fn is_running(&self) -> bool {
-        self.running
+        matches!(
+            self.state,
+            SimState::Running | SimState::Stepping | SimState::SkippingToEnd
+        )
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.state == SimState::Stopped
     }
 
     pub fn start(&mut self) {
-        self.running = true;
+        self.state = SimState::Running;
+    }
+
+    pub fn pause(&mut self) {
+        self.state = SimState::Paused;
+    }
+
+    /// runs a single tick and plays its animation, or finishes playing the current one
+    pub fn step(&mut self) {
+        self.state = SimState::Stepping;
+    }
+
+    pub fn skip_to_end(&mut self) {
+        self.state = SimState::SkippingToEnd;
     }
 
     pub fn stop(&mut self) {
-        self.running = false;
+        self.state = SimState::Stopped;
+    }
+
+    /// playback speed, as a multiple of the normal speed
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
     }
 }
@@ -2,37 +2,33 @@ use bevy::prelude::*;
 
 use crate::{
     core::{
-        dir::Dir,
+        level::{LevelStatus, Progress},
         state::{Effect, State as CoreState},
     },
     game::{
-        constants::GRID_SIZE,
-        map::{Map, MapSetup},
-        simulation::Simulation,
+        constants::{GRID_SIZE, STEP_TIME_MS},
+        level::{CurrentLevel, LevelComplete, LevelProgress, SetupLevel},
+        map::{Dirt, Map, MapSetup},
+        simulation::{SimState, Simulation},
     },
-    ui::rule_editor::Rules,
+    ui::rule_editor::PlayerProgram,
 };
 
-const STEP_TIME_MS: u64 = 500;
+/// upper limit on the ticks run in a single frame while skipping to the end of a run
+const SKIP_TICKS_PER_FRAME: usize = 1000;
 
-#[derive(Component)]
+#[derive(Component, Default)]
 struct Vac {
-    effect: Effect,
-}
-
-impl Vac {
-    fn new(initial_effect: Effect) -> Self {
-        Self {
-            effect: initial_effect,
-        }
-    }
+    /// the effects of the last tick while they're being animated, empty once they've
+    /// played out
+    effects: Vec<Effect>,
 }
 
 #[derive(Component, Deref, DerefMut)]
 struct VacMovementTimer(Timer);
 
 #[derive(Component, Deref, DerefMut)]
-struct State(CoreState);
+pub struct State(CoreState);
 
 impl VacMovementTimer {
     fn new() -> Self {
@@ -44,29 +40,47 @@ impl VacMovementTimer {
     }
 }
 
+/// (Re)spawns the vac at the start of the map whenever the level is set up
+#[allow(clippy::too_many_arguments)]
 fn setup_vac(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    rules: Res<Rules>,
+    mut setup_level: MessageReader<SetupLevel>,
+    mut sim: ResMut<Simulation>,
+    program: Res<PlayerProgram>,
+    level: Option<Res<CurrentLevel>>,
     map: Query<&Map>,
+    existing: Query<Entity, With<Vac>>,
 ) {
-    let map = map.single().unwrap();
-    let mut state = CoreState::new(map.start(), Dir::East);
+    if setup_level.read().count() == 0 {
+        return;
+    }
+
+    let (Some(level), Ok(map)) = (level, map.single()) else {
+        return;
+    };
 
-    // compute starting map location
+    for entity in &existing {
+        commands.entity(entity).despawn();
+    }
+    sim.stop();
+
+    let state = CoreState::at_start(map);
+    let progress = Progress::new(&level, &state, program.rule_count());
+
+    // compute starting map location and heading
     let initial_pos = map.to_game_world(state.vac_pos());
+    let initial_rotation = Quat::from_rotation_z(state.vac_dir().to_radians());
 
-    // execute initial tick
-    let effect = state.tick(map, &rules);
-    let vac = Vac::new(effect);
+    commands.insert_resource(LevelProgress(progress));
 
     // spawn a circle with a triangle to show heading
     commands.spawn((
         Mesh2d(meshes.add(Circle::new(0.4 * GRID_SIZE))),
         MeshMaterial2d(materials.add(Color::WHITE)),
-        Transform::from_translation(initial_pos),
-        vac,
+        Transform::from_translation(initial_pos).with_rotation(initial_rotation),
+        Vac::default(),
         VacMovementTimer::new(),
         State(state),
         // triangle
@@ -82,75 +96,180 @@ fn setup_vac(
     ));
 }
 
+#[allow(clippy::too_many_arguments)]
 fn move_vac(
+    mut commands: Commands,
     mut query: Query<(&mut Transform, &mut Vac, &mut VacMovementTimer, &mut State)>,
     map: Query<&Map>,
-    rules: ResMut<Rules>,
+    dirt: Query<(Entity, &Dirt)>,
+    program: Res<PlayerProgram>,
+    level: Res<CurrentLevel>,
+    mut progress: ResMut<LevelProgress>,
+    mut level_complete: MessageWriter<LevelComplete>,
     time: Res<Time>,
-    sim: Res<Simulation>,
+    mut sim: ResMut<Simulation>,
 ) {
     if !sim.is_running() {
         return;
     }
 
-    let (mut transform, mut vac, mut timer, mut state) = query.single_mut().unwrap();
+    let Ok((mut transform, mut vac, mut timer, mut state)) = query.single_mut() else {
+        return;
+    };
     let map = map.single().unwrap();
-    timer.tick(time.delta());
-
-    // if the timer finished since the last update,
-    // make sure we're at the destination location, then
-    // choose a new direction
-    if timer.is_finished() {
-        // finish moving to the destination point
-        transform.translation = map.to_game_world(state.vac_pos());
-
-        // update state and store in movement state
-        let effect = state.tick(map, &rules);
-        vac.effect = effect;
-    } else {
-        let elapsed = timer.elapsed().as_millis() as f32 / STEP_TIME_MS as f32;
 
-        match vac.effect {
-            Effect::Moved { from, to } => {
-                let pos = Vec2::lerp(from.as_vec2(), to.as_vec2(), elapsed);
-                transform.translation = map.to_game_world(pos);
-            }
-            Effect::Rotated { from, to } => {
-                let from = Quat::from_rotation_z(from.to_radians());
-                let to = Quat::from_rotation_z(to.to_radians());
-                transform.rotation = Quat::slerp(from, to, elapsed);
+    let skipping = sim.state() == SimState::SkippingToEnd;
+    let mut ticks = if skipping { SKIP_TICKS_PER_FRAME } else { 1 };
+
+    // play out the effects of the last tick
+    if !vac.effects.is_empty() {
+        if !skipping {
+            timer.tick(time.delta().mul_f32(sim.speed()));
+
+            if !timer.is_finished() {
+                for effect in &vac.effects {
+                    animate_effect(&mut transform, *effect, timer.fraction(), &state, map);
+                }
+                return;
             }
-            Effect::BumpedWall => {
-                let bump_direction = Vec2::from(state.vac_dir());
-
-                let bump_offset = if elapsed < 0.3 {
-                    // phase 1: move forward at usual speed
-                    let progress = elapsed / 0.3;
-                    bump_direction * 0.2 * progress
-                } else if elapsed < 0.7 {
-                    // phase 2: bounce back
-                    let progress = (elapsed - 0.3) / 0.4;
-                    let forward = 0.2;
-                    let back = -0.15;
-                    bump_direction * (forward + (back - forward) * progress)
-                } else {
-                    // phase 3: small rebound forward to settle
-                    let progress = (elapsed - 0.7) / 0.3;
-                    let back = -0.15;
-                    bump_direction * (back + (0.0 - back) * progress)
-                };
-
-                transform.translation = map.to_game_world(state.vac_pos().as_vec2() + bump_offset);
+        }
+
+        let effects = std::mem::take(&mut vac.effects);
+        finish_effects(&mut commands, &mut transform, &effects, &state, map, &dirt);
+
+        if sim.state() == SimState::Stepping {
+            sim.pause();
+            return;
+        }
+
+        // at high speeds several ticks can be due in one frame, all but the last of which
+        // are skipped over without animation
+        if !skipping {
+            ticks = timer.times_finished_this_tick() as usize;
+        }
+    } else {
+        timer.reset();
+    }
+
+    for i in 0..ticks {
+        // the run is over once the level is complete or out of ticks
+        if progress.status() != LevelStatus::InProgress {
+            sim.stop();
+            return;
+        }
+
+        let effects = state.tick(map, &program, level.physics());
+
+        progress.set_rule_count(program.rule_count());
+        if progress.record(&level, &state, &effects) == LevelStatus::Complete {
+            level_complete.write(LevelComplete {
+                score: progress.score(),
+            });
+        }
+
+        if skipping || i + 1 < ticks {
+            finish_effects(&mut commands, &mut transform, &effects, &state, map, &dirt);
+        } else {
+            vac.effects = effects;
+        }
+    }
+}
+
+/// puts the vac where the effects leave it and removes any dirt it cleaned up
+fn finish_effects(
+    commands: &mut Commands,
+    transform: &mut Transform,
+    effects: &[Effect],
+    state: &CoreState,
+    map: &Map,
+    dirt: &Query<(Entity, &Dirt)>,
+) {
+    transform.translation = map.to_game_world(state.vac_pos());
+    transform.rotation = Quat::from_rotation_z(state.vac_dir().to_radians());
+    transform.scale = Vec3::ONE;
+
+    for effect in effects {
+        let (Effect::Cleaned { to: cleaned, .. } | Effect::Swept { at: cleaned }) = effect else {
+            continue;
+        };
+
+        for (entity, dirt) in dirt {
+            if **dirt == *cleaned {
+                commands.entity(entity).despawn();
             }
         }
     }
 }
 
+/// moves the vac part of the way through an effect, with `elapsed` going from 0 to 1
+fn animate_effect(
+    transform: &mut Transform,
+    effect: Effect,
+    elapsed: f32,
+    state: &CoreState,
+    map: &Map,
+) {
+    match effect {
+        Effect::Moved { from, to } | Effect::Cleaned { from, to } => {
+            let pos = Vec2::lerp(from.as_vec2(), to.as_vec2(), elapsed);
+            transform.translation = map.to_game_world(pos);
+        }
+        Effect::Rotated { from, to } => {
+            let from = Quat::from_rotation_z(from.to_radians());
+            let to = Quat::from_rotation_z(to.to_radians());
+            transform.rotation = Quat::slerp(from, to, elapsed);
+        }
+        Effect::TurnedAround { from, .. } => {
+            // slerping between opposite headings could go either way, so spin explicitly
+            let angle = from.to_radians() + std::f32::consts::PI * elapsed;
+            transform.rotation = Quat::from_rotation_z(angle);
+        }
+        Effect::BumpedWall { dir } => {
+            let bump_direction = Vec2::from(dir);
+
+            let bump_offset = if elapsed < 0.3 {
+                // phase 1: move forward at usual speed
+                let progress = elapsed / 0.3;
+                bump_direction * 0.2 * progress
+            } else if elapsed < 0.7 {
+                // phase 2: bounce back
+                let progress = (elapsed - 0.3) / 0.4;
+                let forward = 0.2;
+                let back = -0.15;
+                bump_direction * (forward + (back - forward) * progress)
+            } else {
+                // phase 3: small rebound forward to settle
+                let progress = (elapsed - 0.7) / 0.3;
+                let back = -0.15;
+                bump_direction * (back + (0.0 - back) * progress)
+            };
+
+            transform.translation = map.to_game_world(state.vac_pos().as_vec2() + bump_offset);
+        }
+        // nothing to animate, swept dirt disappears once the tick has played out
+        Effect::Swept { .. }
+        | Effect::FlagChanged { .. }
+        | Effect::Counted { .. }
+        | Effect::ModeChanged { .. }
+        | Effect::Waited => {}
+        Effect::Beeped => {
+            // pulse in size
+            let pulse = 1. + 0.2 * (elapsed * std::f32::consts::PI).sin();
+            transform.scale = Vec3::splat(pulse);
+        }
+    }
+}
+
 pub struct VacPlugin;
 
 impl Plugin for VacPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_vac.after(MapSetup))
-            .add_systems(Update, move_vac);
+        app.add_systems(
+            Update,
+            (
+                setup_vac.after(MapSetup),
+                move_vac.run_if(resource_exists::<LevelProgress>),
+            ),
+        );
     }
 }
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::game::{
+    constants::GRID_SIZE,
+    map::{Map, MapSetup},
+    vac::State,
+};
+
+pub const VISIT_LABEL_COLOR: Color = Color::hsl(200., 0.6, 0.7);
+
+/// Whether to label each tile with the number of times the vac has been on it
+#[derive(Resource, Default)]
+pub struct VisitOverlay {
+    pub shown: bool,
+}
+
+#[derive(Component, Deref)]
+struct VisitLabel(IVec2);
+
+/// Keeps a label on every visited tile up to date with its visit count. Labels are spawned
+/// as children of the map, so they go away along with it when the level is set up again.
+fn update_visit_labels(
+    mut commands: Commands,
+    overlay: Res<VisitOverlay>,
+    vac: Query<Ref<State>>,
+    map: Query<(Entity, &Map)>,
+    mut labels: Query<(Entity, &VisitLabel, &mut Text2d)>,
+) {
+    if !overlay.shown {
+        for (entity, ..) in &labels {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    let (Ok(state), Ok((map_entity, map))) = (vac.single(), map.single()) else {
+        return;
+    };
+
+    if !state.is_changed() && !overlay.is_changed() {
+        return;
+    }
+
+    let mut existing = labels
+        .iter_mut()
+        .map(|(entity, label, text)| (**label, (entity, text)))
+        .collect::<HashMap<_, _>>();
+
+    for (pt, count) in state.visit_counts() {
+        if let Some((_, mut text)) = existing.remove(pt) {
+            text.0 = count.to_string();
+            continue;
+        }
+
+        // tuck the label into the corner of the tile, out of the way of the vac
+        let offset = Vec3::new(-0.3 * GRID_SIZE, 0.3 * GRID_SIZE, 0.1);
+        let label = commands
+            .spawn((
+                Text2d::new(count.to_string()),
+                TextFont::from_font_size(12.),
+                TextColor(VISIT_LABEL_COLOR),
+                Transform::from_translation(map.to_game_world(*pt) + offset),
+                VisitLabel(*pt),
+            ))
+            .id();
+        commands.entity(map_entity).add_child(label);
+    }
+
+    // anything left over is from an earlier run
+    for (entity, _) in existing.into_values() {
+        commands.entity(entity).despawn();
+    }
+}
+
+pub struct VisitsPlugin;
+
+impl Plugin for VisitsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VisitOverlay>()
+            .add_systems(Update, update_visit_labels.after(MapSetup));
+    }
+}
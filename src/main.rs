@@ -2,12 +2,22 @@ use bevy::prelude::*;
 use bevy_egui::{EguiPlugin, EguiPrimaryContextPass};
 
 use dungeon_vac::{
-    core::{command::Command, rule::Rule, sensor::Sensor},
-    game::{map::MapPlugin, simulation::Simulation, vac::VacPlugin},
+    core::{
+        command::Command, physics::Physics, program::Program, rule::Rule, save, sensor::Sensor,
+        share,
+    },
+    game::{
+        level::{DEFAULT_MAP, LevelPlugin},
+        map::MapPlugin,
+        simulation::Simulation,
+        vac::VacPlugin,
+        visits::VisitsPlugin,
+    },
     ui::{
         camera::CameraPlugin,
         grid::GridPlugin,
-        rule_editor::{RuleEditor, Rules, rule_editor_ui},
+        level_panel::level_panel_ui,
+        rule_editor::{PlayerProgram, ProgramHistory, RuleEditor, rule_editor_ui},
     },
 };
 
@@ -16,7 +26,95 @@ const RULES: [Rule; 2] = [
     Rule::new(Sensor::HitWall, Command::TurnLeft),
 ];
 
+struct Args {
+    /// asset path of the map to start with, eg. "maps/exit.map"
+    map: String,
+    /// program to start with instead of the built-in one, see `save::read_file`
+    rules: Option<String>,
+    /// constraints the vac moves under, unless the level's map sets its own
+    physics: Physics,
+}
+
+fn parse_args() -> Args {
+    let mut map = DEFAULT_MAP.to_string();
+    let mut rules = None;
+    let mut physics = Physics::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--map" => match args.next() {
+                Some(path) => map = path,
+                None => eprintln!("Missing value for --map"),
+            },
+            "--rules" => match args.next() {
+                Some(path) => rules = Some(path),
+                None => eprintln!("Missing value for --rules"),
+            },
+            _ => match physics.apply_arg(&arg, &mut args) {
+                Ok(true) => {}
+                Ok(false) => eprintln!("Ignoring unknown argument: '{arg}'"),
+                Err(err) => eprintln!("{err}"),
+            },
+        }
+    }
+
+    Args {
+        map,
+        rules,
+        physics,
+    }
+}
+
+/// the program saved at the given path, falling back to the built-in one if it can't be read
+fn load_program(path: Option<&str>) -> Program {
+    let Some(path) = path else {
+        return Program::new(RULES);
+    };
+
+    match save::read_file(path) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("Could not load rules from '{path}': {err}");
+            Program::new(RULES)
+        }
+    }
+}
+
+/// the share code in the page's URL, eg. `index.html?code=...`
+#[cfg(target_arch = "wasm32")]
+fn url_share_code() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()?
+        .get("code")
+}
+
+/// there's no URL outside of the browser
+#[cfg(not(target_arch = "wasm32"))]
+fn url_share_code() -> Option<String> {
+    None
+}
+
 fn main() {
+    let args = parse_args();
+    let mut program = load_program(args.rules.as_deref());
+    let mut shared_map = None;
+
+    if let Some(code) = url_share_code() {
+        match share::decode(&code) {
+            Ok(shared) => {
+                program = shared.program;
+                shared_map = shared.map;
+            }
+            Err(err) => eprintln!("Could not import share code from the URL: {err}"),
+        }
+    }
+
+    // the program may not have the mode the editor starts out on
+    let mut editor = RuleEditor::default();
+    editor.reset_selection(&program);
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -30,11 +128,18 @@ fn main() {
         .add_plugins(EguiPlugin::default())
         .add_plugins(CameraPlugin)
         .add_plugins(GridPlugin)
+        .add_plugins(LevelPlugin {
+            map: args.map,
+            shared_map,
+            physics: args.physics,
+        })
         .add_plugins(MapPlugin)
         .add_plugins(VacPlugin)
+        .add_plugins(VisitsPlugin)
         .insert_resource(Simulation::default())
-        .insert_resource(Rules(Vec::from(RULES)))
-        .init_resource::<RuleEditor>()
-        .add_systems(EguiPrimaryContextPass, rule_editor_ui)
+        .insert_resource(PlayerProgram(program))
+        .insert_resource(editor)
+        .init_resource::<ProgramHistory>()
+        .add_systems(EguiPrimaryContextPass, (rule_editor_ui, level_panel_ui))
         .run();
 }
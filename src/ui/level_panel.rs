@@ -0,0 +1,122 @@
+use bevy::{asset::LoadState, prelude::*};
+use bevy_egui::{EguiContexts, egui};
+
+use crate::{
+    core::{
+        level::LevelStatus,
+        register::{Counter, Flag},
+    },
+    game::{
+        level::{AvailableMaps, CurrentLevel, LevelProgress, SelectedMap},
+        map::Map,
+        vac::State as VacState,
+        visits::VisitOverlay,
+    },
+};
+
+#[allow(clippy::too_many_arguments)]
+pub fn level_panel_ui(
+    mut contexts: EguiContexts,
+    mut selected: ResMut<SelectedMap>,
+    available: Res<AvailableMaps>,
+    server: Res<AssetServer>,
+    level: Option<Res<CurrentLevel>>,
+    progress: Option<Res<LevelProgress>>,
+    vac: Query<&VacState>,
+    map: Query<&Map>,
+    mut overlay: ResMut<VisitOverlay>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+
+    egui::SidePanel::right("level_panel")
+        .resizable(false)
+        .min_width(200.0)
+        .show(ctx, |ui| {
+            let mut path = selected.path().to_string();
+            egui::ComboBox::from_label("Map")
+                .selected_text(&path)
+                .show_ui(ui, |ui| {
+                    for map in available.iter() {
+                        ui.selectable_value(&mut path, map.clone(), map);
+                    }
+                });
+
+            if path != selected.path() {
+                selected.select(path);
+            }
+
+            if let Some(handle) = selected.handle()
+                && let LoadState::Failed(err) = server.load_state(handle)
+            {
+                ui.colored_label(
+                    egui::Color32::LIGHT_RED,
+                    format!("Could not load map: {err}"),
+                );
+            }
+
+            ui.separator();
+
+            let Some(level) = level else {
+                ui.label("Loading...");
+                return;
+            };
+
+            ui.label(format!("Goal: {}", level.goal()));
+            if let Some(budget) = level.tick_budget() {
+                ui.label(format!("Tick budget: {budget}"));
+            }
+
+            let physics = level.physics();
+            let ticks = if physics.turn_cooldown == 1 {
+                "tick"
+            } else {
+                "ticks"
+            };
+            ui.label(format!("Turn cooldown: {} {ticks}", physics.turn_cooldown));
+            if physics.bump_costs_tick {
+                ui.label("Bumping into a wall costs a tick");
+            }
+            if physics.turn_and_move {
+                ui.label("The vac can turn and move in one tick");
+            }
+
+            if let (Ok(state), Ok(map)) = (vac.single(), map.single()) {
+                ui.label(format!("Dirt remaining: {}", state.remaining_dirt(map)));
+
+                let flags = Flag::ALL
+                    .into_iter()
+                    .filter(|flag| state.flag(*flag))
+                    .map(|flag| flag.name())
+                    .collect::<Vec<_>>();
+                let counters = Counter::ALL
+                    .into_iter()
+                    .map(|counter| format!("{}={}", counter.name(), state.counter(counter)))
+                    .collect::<Vec<_>>();
+                ui.label(format!("Flags set: {}", flags.join(" ")));
+                ui.label(format!("Counters: {}", counters.join(" ")));
+            }
+
+            // only flag the overlay as changed when it's actually toggled
+            let mut shown = overlay.shown;
+            if ui.checkbox(&mut shown, "Show visit counts").changed() {
+                overlay.shown = shown;
+            }
+
+            if let Some(progress) = &progress {
+                let score = progress.score();
+                ui.label(format!("Ticks: {}  Bumps: {}", score.ticks, score.bumps));
+
+                match progress.status() {
+                    LevelStatus::InProgress => {}
+                    LevelStatus::Complete => {
+                        ui.strong(format!("Level complete! {} points", score.points()));
+                    }
+                    LevelStatus::Failed => {
+                        ui.strong("Out of ticks!");
+                    }
+                }
+            }
+        });
+}
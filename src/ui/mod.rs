@@ -1,3 +1,4 @@
 pub mod camera;
 pub mod grid;
+pub mod level_panel;
 pub mod rule_editor;
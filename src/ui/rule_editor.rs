@@ -1,103 +1,683 @@
 use bevy::prelude::*;
-use bevy_egui::{EguiContexts, egui};
+use bevy_egui::{
+    EguiContexts,
+    egui::{self, Key, Modifiers},
+};
 
 use crate::{
-    core::{command::Command as GameCommand, rule::Rule, sensor::Sensor},
-    game::simulation::Simulation,
+    core::{
+        command::Command as GameCommand,
+        condition::Condition,
+        history::History,
+        level::LevelStatus,
+        program::{DefaultAction, Program},
+        rule::Rule,
+        save,
+        sensor::Sensor,
+        share,
+    },
+    game::{
+        level::{CurrentLevel, LevelProgress, SelectedMap, SetupLevel},
+        simulation::{MAX_SPEED, MIN_SPEED, SimState, Simulation},
+        vac::State as VacState,
+    },
 };
 
-#[derive(Default, Resource)]
+pub const DEFAULT_PROGRAM_FILE: &str = "program.json";
+
+#[derive(Resource)]
 pub struct RuleEditor {
-    pub selected_sensor: usize,
-    pub selected_command: usize,
+    /// the condition of the rule being created
+    pub condition: Condition,
+    /// the command of the rule being created
+    pub command: GameCommand,
+    /// the mode whose rules are shown and edited
+    pub selected_mode: usize,
+    /// the name being typed in for the selected mode
+    pub mode_name: String,
+    /// index of the rule in the selected mode that's being edited in place
+    pub editing: Option<usize>,
+    /// where the program is saved to and loaded from
+    pub file_path: String,
+    /// how the last save, load or share went
+    pub status: Option<String>,
+    /// share code being pasted in, or the last one that was copied
+    pub share_code: String,
+    /// whether copied share codes include the current map
+    pub share_map: bool,
+}
+
+impl Default for RuleEditor {
+    fn default() -> Self {
+        Self {
+            condition: Condition::Sensor(Sensor::ALL[0]),
+            command: GameCommand::TurnRight,
+            selected_mode: 0,
+            mode_name: Program::MAIN_MODE.into(),
+            editing: None,
+            file_path: DEFAULT_PROGRAM_FILE.into(),
+            status: None,
+            share_code: String::new(),
+            share_map: false,
+        }
+    }
+}
+
+impl RuleEditor {
+    /// selects a mode, or the last one if there's no mode at `idx`, and shows its name
+    pub fn select_mode(&mut self, program: &Program, idx: usize) {
+        self.selected_mode = idx.min(program.modes().len() - 1);
+        self.mode_name = program.modes()[self.selected_mode].name().into();
+    }
+
+    /// Stops editing and keeps the selected mode in range, for when the whole program was
+    /// replaced, eg. by loading or undoing
+    pub fn reset_selection(&mut self, program: &Program) {
+        self.editing = None;
+        self.select_mode(program, self.selected_mode);
+    }
+}
+
+/// The kinds of node a condition can be switched between in the editor
+#[derive(Clone, Copy, PartialEq)]
+enum ConditionKind {
+    Sensor,
+    And,
+    Or,
+    Not,
+}
+
+impl ConditionKind {
+    const ALL: [ConditionKind; 4] = [
+        ConditionKind::Sensor,
+        ConditionKind::And,
+        ConditionKind::Or,
+        ConditionKind::Not,
+    ];
+
+    fn of(condition: &Condition) -> Self {
+        match condition {
+            Condition::Sensor(_) => ConditionKind::Sensor,
+            Condition::And(_) => ConditionKind::And,
+            Condition::Or(_) => ConditionKind::Or,
+            Condition::Not(_) => ConditionKind::Not,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ConditionKind::Sensor => "sensor",
+            ConditionKind::And => "AND",
+            ConditionKind::Or => "OR",
+            ConditionKind::Not => "NOT",
+        }
+    }
+
+    /// turns the condition into this kind of node, keeping as much of it as possible
+    fn convert(self, condition: Condition) -> Condition {
+        match (self, condition) {
+            (ConditionKind::Sensor, Condition::Sensor(sensor)) => Condition::Sensor(sensor),
+            (ConditionKind::Sensor, _) => Condition::Sensor(Sensor::ALL[0]),
+            (ConditionKind::And, Condition::And(conditions) | Condition::Or(conditions)) => {
+                Condition::And(conditions)
+            }
+            (ConditionKind::And, condition) => Condition::And(vec![condition]),
+            (ConditionKind::Or, Condition::And(conditions) | Condition::Or(conditions)) => {
+                Condition::Or(conditions)
+            }
+            (ConditionKind::Or, condition) => Condition::Or(vec![condition]),
+            (ConditionKind::Not, Condition::Not(condition)) => Condition::Not(condition),
+            (ConditionKind::Not, condition) => Condition::Not(Box::new(condition)),
+        }
+    }
+}
+
+/// Picks a sensor, along with the threshold for counter sensors
+fn sensor_ui(ui: &mut egui::Ui, sensor: &mut Sensor) {
+    // counter sensors are listed once per counter, whatever their threshold
+    let same_kind = |a: Sensor, b: Sensor| match (a, b) {
+        (Sensor::CounterAbove(a, _), Sensor::CounterAbove(b, _)) => a == b,
+        _ => a == b,
+    };
+    let label = |sensor: Sensor| match sensor {
+        Sensor::CounterAbove(counter, _) => format!("counter {} above", counter.name()),
+        _ => sensor.name(),
+    };
+
+    egui::ComboBox::from_id_salt("sensor")
+        .selected_text(label(*sensor))
+        .show_ui(ui, |ui| {
+            for option in Sensor::ALL {
+                let selected = same_kind(*sensor, option);
+                if ui.selectable_label(selected, label(option)).clicked() && !selected {
+                    *sensor = option;
+                }
+            }
+        });
+
+    if let Sensor::CounterAbove(_, threshold) = sensor {
+        ui.add(egui::DragValue::new(threshold));
+    }
+}
+
+/// Editor for a condition tree, with the children of AND, OR and NOT nodes indented below
+fn condition_ui(ui: &mut egui::Ui, condition: &mut Condition) {
+    let mut kind = ConditionKind::of(condition);
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_salt("kind")
+            .width(60.0)
+            .selected_text(kind.label())
+            .show_ui(ui, |ui| {
+                for option in ConditionKind::ALL {
+                    ui.selectable_value(&mut kind, option, option.label());
+                }
+            });
+
+        if let Condition::Sensor(sensor) = condition {
+            sensor_ui(ui, sensor);
+        }
+    });
+
+    if kind != ConditionKind::of(condition) {
+        let old = std::mem::replace(condition, Condition::And(Vec::new()));
+        *condition = kind.convert(old);
+    }
+
+    match condition {
+        Condition::Sensor(_) => {}
+        Condition::Not(condition) => {
+            ui.indent("not", |ui| condition_ui(ui, condition));
+        }
+        Condition::And(conditions) | Condition::Or(conditions) => {
+            ui.indent("operands", |ui| {
+                let mut remove_idx = None;
+                for (idx, condition) in conditions.iter_mut().enumerate() {
+                    ui.push_id(idx, |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("X").clicked() {
+                                remove_idx = Some(idx);
+                            }
+                            ui.vertical(|ui| condition_ui(ui, condition));
+                        });
+                    });
+                }
+
+                if let Some(idx) = remove_idx {
+                    conditions.remove(idx);
+                }
+
+                if ui.small_button("+").clicked() {
+                    conditions.push(Condition::Sensor(Sensor::ALL[0]));
+                }
+            });
+        }
+    }
+}
+
+/// Picks a command, along with the mode to go to for "go to mode" commands
+fn command_ui(ui: &mut egui::Ui, command: &mut GameCommand, modes: &[String]) {
+    // the mode to go to is picked separately
+    let label = |command: &GameCommand| match command {
+        GameCommand::GoToMode(_) => "THEN go to mode".to_string(),
+        _ => command.to_string(),
+    };
+    let same_kind = |a: &GameCommand, b: &GameCommand| match (a, b) {
+        (GameCommand::GoToMode(_), GameCommand::GoToMode(_)) => true,
+        _ => a == b,
+    };
+
+    egui::ComboBox::from_id_salt("command")
+        .selected_text(label(command))
+        .show_ui(ui, |ui| {
+            for option in GameCommand::PLAYER {
+                let selected = same_kind(command, &option);
+                if ui.selectable_label(selected, label(&option)).clicked() && !selected {
+                    *command = match option {
+                        GameCommand::GoToMode(_) => GameCommand::GoToMode(modes[0].clone()),
+                        option => option,
+                    };
+                }
+            }
+        });
+
+    if let GameCommand::GoToMode(target) = command {
+        egui::ComboBox::from_id_salt("target_mode")
+            .selected_text(target.as_str())
+            .show_ui(ui, |ui| {
+                for mode in modes {
+                    ui.selectable_value(target, mode.clone(), mode);
+                }
+            });
+    }
 }
 
 // FIXME: this isn't the right place for this
 #[derive(Default, Resource, Deref, DerefMut)]
-pub struct Rules(pub Vec<Rule>);
+pub struct PlayerProgram(pub Program);
+
+/// Earlier versions of the player's program, for undo and redo. Importing a share code that
+/// comes with a map clears it, since switching maps can't be undone.
+#[derive(Default, Resource, Deref, DerefMut)]
+pub struct ProgramHistory(pub History<Program>);
 
+#[allow(clippy::too_many_arguments)]
 pub fn rule_editor_ui(
     mut contexts: EguiContexts,
     mut editor: ResMut<RuleEditor>,
-    mut rules: ResMut<Rules>,
+    mut program: ResMut<PlayerProgram>,
+    mut history: ResMut<ProgramHistory>,
     mut sim: ResMut<Simulation>,
+    progress: Option<Res<LevelProgress>>,
+    level: Option<Res<CurrentLevel>>,
+    mut selected_map: ResMut<SelectedMap>,
+    mut setup_level: MessageWriter<SetupLevel>,
+    vac: Query<&VacState>,
 ) {
-    let sensors = [Sensor::HitWall, Sensor::SpaceLeft, Sensor::SpaceRight];
-    let commands = [GameCommand::TurnRight, GameCommand::TurnLeft];
-
     let Ok(ctx) = contexts.ctx_mut() else {
         return;
     };
 
-    let running = sim.is_running();
+    let state = sim.state();
+    // rules can only be changed while no run is underway
+    let editable = sim.is_stopped();
+    // a run can only start once a level is loaded and it isn't over yet
+    let ready = progress
+        .as_ref()
+        .is_some_and(|progress| progress.status() == LevelStatus::InProgress);
+    // the mode the vac is in, once a run is underway
+    let active_mode = vac
+        .single()
+        .ok()
+        .filter(|_| !editable)
+        .map(|state| state.mode());
+
+    // modes can be removed, so keep the selections in range
+    if editor.selected_mode >= program.modes().len() {
+        let selected = editor.selected_mode;
+        editor.select_mode(&program, selected);
+    }
+    let mode_names = program
+        .modes()
+        .iter()
+        .map(|mode| mode.name().to_string())
+        .collect::<Vec<_>>();
+
+    // edits are recorded by comparing the program before and after the panel is drawn
+    let before = program.0.clone();
+    let editing_before = editor.editing;
+    // what the edit made this frame was, if it isn't a change to the rule being edited
+    let mut edit = None;
+    // set by edits that can't be undone, since they change more than the program
+    let mut clear_history = false;
+
+    let mut undo = false;
+    let mut redo = false;
+    // text fields have their own undo
+    if editable && !ctx.wants_keyboard_input() {
+        ctx.input_mut(|input| {
+            // checked first since ctrl+Z would also match with shift held
+            redo = input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+            undo = !redo && input.consume_key(Modifiers::COMMAND, Key::Z);
+        });
+    }
 
     egui::SidePanel::left("rule_editor")
         .resizable(false)
         .min_width(200.0)
         .show(ctx, |ui| {
             ui.horizontal(|ui| {
-                ui.add_enabled_ui(!running, |ui| {
-                    if ui.button("Start").clicked() {
-                        sim.start();
+                match state {
+                    SimState::Running | SimState::SkippingToEnd => {
+                        if ui.button("Pause").clicked() {
+                            sim.pause();
+                        }
+                    }
+                    SimState::Paused | SimState::Stepping => {
+                        ui.add_enabled_ui(state == SimState::Paused, |ui| {
+                            if ui.button("Resume").clicked() {
+                                sim.start();
+                            }
+                        });
+                    }
+                    SimState::Stopped => {
+                        ui.add_enabled_ui(ready, |ui| {
+                            if ui.button("Start").clicked() {
+                                sim.start();
+                            }
+                        });
+                    }
+                }
+
+                let playing = matches!(state, SimState::Running | SimState::SkippingToEnd);
+                ui.add_enabled_ui(ready && !playing, |ui| {
+                    if ui.button("Step").clicked() {
+                        sim.step();
                     }
                 });
 
-                ui.add_enabled_ui(running, |ui| {
-                    if ui.button("Stop").clicked() {
-                        sim.stop();
+                ui.add_enabled_ui(ready && state != SimState::SkippingToEnd, |ui| {
+                    if ui.button("Skip to End").clicked() {
+                        sim.skip_to_end();
+                    }
+                });
+
+                // rebuilding the level puts the vac and dirt back and stops the run
+                ui.add_enabled_ui(progress.is_some(), |ui| {
+                    if ui.button("Reset").clicked() {
+                        setup_level.write(SetupLevel);
+                    }
+                });
+            });
+
+            let mut speed = sim.speed();
+            let slider = egui::Slider::new(&mut speed, MIN_SPEED..=MAX_SPEED)
+                .logarithmic(true)
+                .suffix("x")
+                .text("Speed");
+            if ui.add(slider).changed() {
+                sim.set_speed(speed);
+            }
+
+            ui.add_enabled_ui(editable, |ui| {
+                ui.horizontal(|ui| {
+                    let undo_label = history.undo_label().unwrap_or_default();
+                    let response =
+                        ui.add_enabled(!undo_label.is_empty(), egui::Button::new("Undo"));
+                    if response.on_hover_text(undo_label).clicked() {
+                        undo = true;
+                    }
+
+                    let redo_label = history.redo_label().unwrap_or_default();
+                    let response =
+                        ui.add_enabled(!redo_label.is_empty(), egui::Button::new("Redo"));
+                    if response.on_hover_text(redo_label).clicked() {
+                        redo = true;
                     }
                 });
             });
 
             ui.separator();
 
-            ui.label("Create Rule:");
-            ui.add_space(8.0);
+            ui.label("Modes:");
+            ui.horizontal_wrapped(|ui| {
+                for (idx, mode) in program.modes().iter().enumerate() {
+                    // the mode the vac is running in is marked
+                    let label = if active_mode == Some(idx) {
+                        format!("> {}", mode.name())
+                    } else {
+                        mode.name().to_string()
+                    };
+                    if ui
+                        .selectable_label(editor.selected_mode == idx, label)
+                        .clicked()
+                    {
+                        editor.select_mode(&program, idx);
+                        editor.editing = None;
+                    }
+                }
 
-            ui.add_enabled_ui(!running, |ui| {
-                egui::ComboBox::from_label("Sensor")
-                    .selected_text(sensors[editor.selected_sensor])
-                    .show_ui(ui, |ui| {
-                        for (i, sensor) in sensors.iter().enumerate() {
-                            ui.selectable_value(&mut editor.selected_sensor, i, *sensor);
+                ui.add_enabled_ui(editable, |ui| {
+                    if ui.small_button("+").clicked() {
+                        let idx = program.add_mode();
+                        edit = Some("add mode");
+                        editor.editing = None;
+                        editor.select_mode(&program, idx);
+                    }
+                });
+            });
+
+            ui.add_enabled_ui(editable, |ui| {
+                ui.horizontal(|ui| {
+                    let editor = &mut *editor;
+                    ui.add(egui::TextEdit::singleline(&mut editor.mode_name).desired_width(80.0));
+                    if ui.button("Rename").clicked() {
+                        if program.rename_mode(editor.selected_mode, &editor.mode_name) {
+                            edit = Some("rename mode");
+                        } else {
+                            // put back the name that's still in use
+                            editor.select_mode(&program, editor.selected_mode);
                         }
-                    });
+                    }
 
-                egui::ComboBox::from_label("Command")
-                    .selected_text(commands[editor.selected_command])
-                    .show_ui(ui, |ui| {
-                        for (i, command) in commands.iter().enumerate() {
-                            ui.selectable_value(&mut editor.selected_command, i, *command);
+                    ui.add_enabled_ui(program.modes().len() > 1, |ui| {
+                        if ui.button("Delete").clicked() {
+                            program.remove_mode(editor.selected_mode);
+                            edit = Some("delete mode");
+                            editor.editing = None;
+                            editor.select_mode(&program, editor.selected_mode.saturating_sub(1));
                         }
                     });
+                });
+            });
+
+            ui.separator();
+
+            ui.label("Create Rule:");
+            ui.add_space(8.0);
+
+            ui.add_enabled_ui(editable, |ui| {
+                ui.label("WHEN");
+                ui.push_id("condition", |ui| condition_ui(ui, &mut editor.condition));
+
+                ui.push_id("new_command", |ui| {
+                    command_ui(ui, &mut editor.command, &mode_names)
+                });
 
                 ui.add_space(8.0);
                 if ui.button("Add Rule").clicked() {
-                    let sensor = sensors[editor.selected_sensor];
-                    let command = commands[editor.selected_command];
-                    rules.push(Rule::new(sensor, command));
+                    let rule =
+                        Rule::with_condition(editor.condition.clone(), editor.command.clone());
+                    let rules = program.mode_mut(editor.selected_mode).unwrap().rules_mut();
+                    rules.push(rule);
+                    edit = Some("add rule");
                 }
             });
 
             ui.separator();
 
-            ui.label("Rules:");
+            let mode = program.mode_mut(editor.selected_mode).unwrap();
+            ui.label(format!("Rules in mode {}:", mode.name()));
             ui.add_space(8.0);
 
+            // rules are checked from the top down, so their order sets their priority
+            let rule_count = mode.rules().len();
             let mut remove_idx = None;
-            for (idx, rule) in rules.0.iter().enumerate() {
-                ui.horizontal(|ui| {
-                    ui.label(format!("{} {}", rule.sensor(), rule.command()));
-                    ui.add_enabled_ui(!running, |ui| {
-                        if ui.button("X").clicked() {
-                            remove_idx = Some(idx);
+            let mut move_up_idx = None;
+            for (idx, rule) in mode.rules_mut().iter_mut().enumerate() {
+                ui.push_id(idx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add_enabled_ui(editable, |ui| {
+                            let mut enabled = rule.is_enabled();
+                            if ui.checkbox(&mut enabled, "").changed() {
+                                rule.set_enabled(enabled);
+                                edit = Some(if enabled {
+                                    "enable rule"
+                                } else {
+                                    "disable rule"
+                                });
+                            }
+                        });
+
+                        let text = egui::RichText::new(rule.to_string());
+                        if rule.is_enabled() {
+                            ui.label(text);
+                        } else {
+                            ui.label(text.strikethrough().weak());
                         }
                     });
+
+                    ui.add_enabled_ui(editable, |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(idx > 0, egui::Button::new("^")).clicked() {
+                                move_up_idx = Some(idx);
+                            }
+                            let last = idx + 1 == rule_count;
+                            if ui.add_enabled(!last, egui::Button::new("v")).clicked() {
+                                move_up_idx = Some(idx + 1);
+                            }
+
+                            let editing = editor.editing == Some(idx);
+                            if ui.selectable_label(editing, "Edit").clicked() {
+                                editor.editing = (!editing).then_some(idx);
+                            }
+                            if ui.button("X").clicked() {
+                                remove_idx = Some(idx);
+                            }
+                        });
+                    });
+
+                    if editable && editor.editing == Some(idx) {
+                        ui.indent("edit", |ui| {
+                            ui.label("WHEN");
+                            ui.push_id("condition", |ui| {
+                                condition_ui(ui, rule.condition_mut());
+                            });
+                            command_ui(ui, rule.command_mut(), &mode_names);
+                        });
+                    }
+                });
+            }
+
+            if let Some(idx) = move_up_idx {
+                mode.rules_mut().swap(idx - 1, idx);
+                edit = Some("move rule");
+                // keep editing the same rule wherever it ends up
+                editor.editing = editor.editing.map(|editing| match editing {
+                    _ if editing == idx => idx - 1,
+                    _ if editing == idx - 1 => idx,
+                    _ => editing,
                 });
             }
 
             if let Some(idx) = remove_idx {
-                rules.remove(idx);
+                mode.rules_mut().remove(idx);
+                edit = Some("remove rule");
+                editor.editing = editor
+                    .editing
+                    .filter(|editing| *editing != idx)
+                    .map(|editing| if editing > idx { editing - 1 } else { editing });
+            }
+
+            ui.add_space(8.0);
+
+            ui.add_enabled_ui(editable, |ui| {
+                let mut default_action = program.default_action();
+                egui::ComboBox::from_id_salt("default_action")
+                    .selected_text(default_action)
+                    .show_ui(ui, |ui| {
+                        for action in DefaultAction::ALL {
+                            ui.selectable_value(&mut default_action, action, action);
+                        }
+                    });
+                if default_action != program.default_action() {
+                    program.set_default_action(default_action);
+                    edit = Some("change default action");
+                }
+            });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                let editor = &mut *editor;
+                ui.add(egui::TextEdit::singleline(&mut editor.file_path).desired_width(100.0));
+
+                if ui.button("Save").clicked() {
+                    let result = save::write_file(&editor.file_path, &program);
+                    editor.status = Some(match result {
+                        Ok(()) => format!("Saved to {}", editor.file_path),
+                        Err(err) => format!("Could not save: {err}"),
+                    });
+                }
+
+                ui.add_enabled_ui(editable, |ui| {
+                    if ui.button("Load").clicked() {
+                        let result = save::read_file(&editor.file_path);
+                        editor.status = Some(match result {
+                            Ok(loaded) => {
+                                program.0 = loaded;
+                                editor.reset_selection(&program);
+                                edit = Some("load program");
+                                format!("Loaded {}", editor.file_path)
+                            }
+                            Err(err) => format!("Could not load: {err}"),
+                        });
+                    }
+                });
+            });
+
+            ui.horizontal(|ui| {
+                let editor = &mut *editor;
+                ui.add(egui::TextEdit::singleline(&mut editor.share_code).desired_width(100.0));
+
+                if ui.button("Copy code").clicked() {
+                    let map = level
+                        .as_ref()
+                        .filter(|_| editor.share_map)
+                        .map(|level| level.map());
+                    editor.share_code = share::encode(&program, map);
+                    ui.ctx().copy_text(editor.share_code.clone());
+                    editor.status = Some("Copied share code".into());
+                }
+
+                ui.add_enabled_ui(editable, |ui| {
+                    if ui.button("Import code").clicked() {
+                        editor.status = Some(match share::decode(&editor.share_code) {
+                            Ok(shared) => {
+                                program.0 = shared.program;
+                                editor.reset_selection(&program);
+                                edit = Some("import code");
+
+                                // the history only holds programs, so undoing would leave the
+                                // old program on the new map
+                                if let Some(map) = shared.map {
+                                    selected_map.select_shared(map);
+                                    clear_history = true;
+                                    "Imported share code and its map, which can't be undone".into()
+                                } else {
+                                    "Imported share code".into()
+                                }
+                            }
+                            Err(err) => format!("Could not import: {err}"),
+                        });
+                    }
+                });
+            });
+            ui.checkbox(&mut editor.share_map, "Include map in share code");
+
+            if let Some(status) = &editor.status {
+                ui.label(status);
             }
         });
+
+    if program.0 != before {
+        match (edit, editor.editing) {
+            (Some(label), _) => history.record(label, before),
+            // changes to the rule being edited come in bit by bit, and are undone together
+            (None, Some(idx)) => history.record_merged(format!("edit rule {}", idx + 1), before),
+            (None, None) => history.record("edit", before),
+        }
+    }
+
+    // reopening the same rule later starts a new edit instead of adding to the old one
+    if editor.editing != editing_before {
+        history.stop_merging();
+    }
+
+    if clear_history {
+        history.clear();
+    }
+
+    if undo || redo {
+        let current = std::mem::take(&mut program.0);
+        let (Ok(restored) | Err(restored)) = if undo {
+            history.undo(current)
+        } else {
+            history.redo(current)
+        };
+        program.0 = restored;
+
+        // the rule being edited may well be gone
+        editor.reset_selection(&program);
+    }
 }